@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+use crate::error::{self, ErrorCode};
+
+/// A token telling long-running commands, such as those sampling over an
+/// interval, that they were interrupted with Ctrl-C.
+///
+/// The token is shared by cloning it, so that every loop observes the same
+/// interruption, rather than each listening for Ctrl-C on its own. A loop
+/// interrupted this way stops early, and returns what it gathered so far
+/// when it can, or an interrupted error otherwise.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    /// Creates a token which is never cancelled, unless done explicitly.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Creates a token cancelled by the first Ctrl-C.
+    ///
+    /// Once the token is cancelled, a second Ctrl-C exits right away with
+    /// the conventional status of processes interrupted by SIGINT, in case
+    /// a loop fails to observe the token.
+    pub fn on_ctrl_c() -> CancellationToken {
+        let token = CancellationToken::new();
+
+        let listener = token.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            listener.cancel();
+
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(ErrorCode::Interrupted.exit_code());
+            }
+        });
+
+        token
+    }
+
+    /// Cancels the token, waking up every loop waiting on it.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Returns whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Waits until the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            // The notification is awaited from before the flag is checked,
+            // so that a cancellation in between is not missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+
+    /// Sleeps for the provided duration, unless the token is cancelled
+    /// first, and returns whether it slept for the whole duration.
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => true,
+            _ = self.cancelled() => false,
+        }
+    }
+}
+
+/// Creates the error a loop returns when it was interrupted before it
+/// gathered anything worth returning.
+pub fn interrupted(message: impl Into<String>) -> anyhow::Error {
+    error::categorized(ErrorCode::Interrupted, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn sleep_stops_once_cancelled() {
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            canceller.cancel();
+        });
+
+        let started_at = Instant::now();
+        assert!(!token.sleep(Duration::from_secs(30)).await);
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+        assert!(token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn sleep_completes_unless_cancelled() {
+        assert!(CancellationToken::new().sleep(Duration::from_millis(10)).await);
+    }
+}
@@ -1,10 +1,16 @@
 use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, Local};
 use rsntp::AsyncSntpClient;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::{self, CancellationToken};
+use crate::error::{self, ErrorCode};
+use crate::theme;
 
 /// Returns the system date.
 pub async fn date() -> Result<Date> {
@@ -14,13 +20,22 @@ pub async fn date() -> Result<Date> {
     Ok(now_with_tz.into())
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Date {
     day_name: String,
     day_number: u8,
     month_name: String,
     year: i32,
     week_number: u8,
+
+    /// The date in ISO 8601 format, such as 2023-04-08.
+    iso_date: String,
+
+    /// The day of the year, starting at 1 for January 1st.
+    ordinal: u16,
+
+    /// The day of the week, starting at 1 for Monday.
+    weekday_number: u8,
 }
 
 impl Display for Date {
@@ -40,39 +55,230 @@ impl From<DateTime<Local>> for Date {
             month_name: dt.format("%B").to_string(),
             year: dt.format("%Y").to_string().parse::<i32>().unwrap(),
             week_number: dt.format("%U").to_string().parse::<u8>().unwrap(),
+            iso_date: dt.format("%Y-%m-%d").to_string(),
+            ordinal: dt.ordinal() as u16,
+            weekday_number: dt.weekday().number_from_monday() as u8,
         }
     }
 }
 
+/// The NTP servers the time is synchronized with, tried in order.
+pub const NTP_SERVERS: &[&str] = &["pool.ntp.org", "time.cloudflare.com", "time.google.com"];
+
+/// The maximum duration to wait for a single NTP server to respond.
+pub const NTP_SERVER_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// Returns the system time.
-pub async fn time() -> Result<Time> {
+///
+/// The time is synchronized with the first of the NTP servers to respond,
+/// the provided server being tried before the default ones.
+///
+/// # Errors
+///
+/// If none of the NTP servers responds in time.
+pub async fn time(server: Option<&str>) -> Result<Time> {
+    let servers = server
+        .into_iter()
+        .chain(NTP_SERVERS.iter().copied())
+        .collect::<Vec<&str>>();
+
+    time_from(&servers, NTP_SERVER_TIMEOUT).await
+}
+
+/// Returns the system time, synchronized with the first of the provided
+/// NTP servers to respond within the timeout.
+async fn time_from(servers: &[&str], timeout: Duration) -> Result<Time> {
     let sntp_client = AsyncSntpClient::new();
-    let sntp_time = sntp_client.synchronize("pool.ntp.org").await?;
+
+    synchronize_with_first(servers, timeout, |server| {
+        let sntp_client = &sntp_client;
+        async move {
+            let sntp_time = sntp_client.synchronize(server).await?;
+            time_of(&sntp_time)
+        }
+    })
+    .await
+}
+
+/// Returns the time synchronized with the first of the provided servers for
+/// which `synchronize` succeeds within the timeout, the servers it fails
+/// for being reported in the error when none succeeds.
+async fn synchronize_with_first<'a, F, Fut>(servers: &[&'a str], timeout: Duration, synchronize: F) -> Result<Time>
+where
+    F: Fn(&'a str) -> Fut,
+    Fut: Future<Output = Result<Time>>,
+{
+    let mut failures = Vec::new();
+
+    for server in servers {
+        // A response that cannot be converted is a failure of that server
+        // alone, just like no response: the next ones may still respond
+        // with a valid time
+        match tokio::time::timeout(timeout, synchronize(server)).await {
+            Ok(Ok(mut t)) => {
+                t.ntp_server = server.to_string();
+                return Ok(t);
+            }
+            Ok(Err(err)) => failures.push(format!("{}: {}", server, err)),
+            Err(_) => failures.push(format!("{}: timed out after {}s", server, timeout.as_secs())),
+        }
+    }
+
+    Err(error::categorized(
+        ErrorCode::NtpFailure,
+        format!("no NTP server responded; {}", failures.join("; ")),
+    ))
+}
+
+/// Returns the system time, as synchronized with an NTP server.
+fn time_of(sntp_time: &rsntp::SynchronizationResult) -> Result<Time> {
     let now = sntp_time.datetime().into_chrono_datetime()?;
     let now_with_tz = now.with_timezone(&Local);
 
     let mut t = Time::from(now_with_tz);
     t.offset = sntp_time.clock_offset().as_secs_f64();
+    t.offset_ms = (t.offset * 1000.0).round() as i64;
+    t.round_trip_delay = sntp_time.round_trip_delay().abs_as_std_duration()?;
+    t.ntp_details = Some(NtpDetails::from(sntp_time));
 
     Ok(t)
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Time {
     hour: u8,
     minute: u8,
     second: u8,
     timezone: String,
+
+    /// The offset from the NTP server's clock, in seconds.
     offset: f64,
+
+    /// The offset from the NTP server's clock, rounded to milliseconds.
+    offset_ms: i64,
+
+    /// The time the request to the NTP server and its response spent in
+    /// transit, in seconds: the exchange's total duration, minus the time the
+    /// server took to process the request.
+    #[serde(rename = "round_trip_delay_secs", with = "crate::serde_duration")]
+    round_trip_delay: Duration,
+
+    /// The NTP server the time was synchronized with.
+    ntp_server: String,
+
+    /// The details of the NTP server's response, only kept when requested.
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    ntp_details: Option<NtpDetails>,
+
+    /// Whether to omit the offset from the text output.
+    #[serde(skip)]
+    no_offset: bool,
+
+    /// Whether to display the round-trip delay in the text output.
+    #[serde(skip)]
+    verbose: bool,
+}
+
+impl Time {
+    /// Returns the offset from the NTP server's clock, in seconds.
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Sets whether the offset from the NTP server's clock is omitted from
+    /// the text output. It is still serialized.
+    pub fn no_offset(mut self, no_offset: bool) -> Self {
+        self.no_offset = no_offset;
+        self
+    }
+
+    /// Sets whether the round-trip delay to the NTP server is displayed in
+    /// the text output. It is always serialized.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Sets whether the details of the NTP server's response are kept, to be
+    /// both displayed and serialized.
+    pub fn ntp_details(mut self, ntp_details: bool) -> Self {
+        if !ntp_details {
+            self.ntp_details = None;
+        }
+        self
+    }
+}
+
+/// The details of an NTP server's response, describing the quality of the
+/// time source.
+#[derive(Deserialize, Serialize)]
+pub struct NtpDetails {
+    /// The distance of the NTP server from a reference clock: 1 for a server
+    /// attached to one, such as a GPS receiver, 2 for a server synchronized
+    /// with a stratum 1 server, and so on.
+    stratum: u8,
+
+    /// The leap second warning of the NTP server: no_warning,
+    /// last_minute_has_61_seconds, last_minute_has_59_seconds, or
+    /// alarm_condition when the server's clock is not synchronized.
+    leap_indicator: String,
+
+    /// The identifier of the NTP server's reference: the code of its
+    /// reference clock, such as GPS, for stratum 1 servers, or the address
+    /// of its upstream server otherwise.
+    reference_identifier: String,
+}
+
+impl From<&rsntp::SynchronizationResult> for NtpDetails {
+    fn from(result: &rsntp::SynchronizationResult) -> Self {
+        let leap_indicator = match result.leap_indicator() {
+            rsntp::LeapIndicator::NoWarning => "no_warning",
+            rsntp::LeapIndicator::LastMinuteHas61Seconds => "last_minute_has_61_seconds",
+            rsntp::LeapIndicator::LastMinuteHas59Seconds => "last_minute_has_59_seconds",
+            rsntp::LeapIndicator::AlarmCondition => "alarm_condition",
+        };
+
+        NtpDetails {
+            stratum: result.stratum(),
+            leap_indicator: leap_indicator.to_string(),
+            reference_identifier: result.reference_identifier().to_string(),
+        }
+    }
 }
 
 impl Display for Time {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
         write!(f, "{}", self.hour.to_string().bold())?;
         write!(f, ":{}", self.minute.to_string().bold())?;
-        write!(f, ":{}", self.second.to_string())?;
-        write!(f, " UTC {}", self.timezone.bright_cyan())?;
-        write!(f, "\n±{:.4} seconds", self.offset.to_string().bright_magenta())
+        write!(f, ":{}", self.second)?;
+        write!(f, " UTC {}", self.timezone.color(palette.accent))?;
+
+        if self.no_offset {
+            return Ok(());
+        }
+
+        write!(
+            f,
+            "\n±{:.4} seconds from {}",
+            self.offset.to_string().color(palette.highlight),
+            self.ntp_server.color(palette.muted)
+        )?;
+
+        if self.verbose {
+            write!(f, "\nround-trip delay {:.4} seconds", self.round_trip_delay.as_secs_f64())?;
+        }
+
+        if let Some(details) = &self.ntp_details {
+            write!(
+                f,
+                "\nstratum {}, reference {}, leap indicator {}",
+                details.stratum, details.reference_identifier, details.leap_indicator
+            )?;
+        }
+
+        Ok(())
     }
 }
 
@@ -84,27 +290,255 @@ impl From<DateTime<Local>> for Time {
             second: dt.format("%S").to_string().parse::<u8>().unwrap(),
             timezone: dt.format("%Z").to_string(),
             offset: 0.0,
+            offset_ms: 0,
+            round_trip_delay: Duration::ZERO,
+            ntp_server: String::new(),
+            ntp_details: None,
+            no_offset: false,
+            verbose: false,
         }
     }
 }
 
+/// Returns the drift rate of the system clock, measured by synchronizing
+/// with an NTP server twice, the provided interval apart.
+///
+/// The time is synchronized with the first of the NTP servers to respond,
+/// the provided server being tried before the default ones. Both
+/// synchronizations should reach the same server for the rate to be
+/// meaningful, which providing a server makes likelier.
+///
+/// # Errors
+///
+/// If either synchronization fails, or if the token is cancelled before
+/// the second one. When the second one does not happen, the error still
+/// reports the offset the first one measured.
+pub async fn drift(server: Option<&str>, interval: Duration, cancellation: &CancellationToken) -> Result<Drift> {
+    let first = time(server).await.context("the first synchronization failed")?;
+    let first_at = Instant::now();
+
+    if !cancellation.sleep(interval).await {
+        return Err(cancel::interrupted(format!(
+            "interrupted before the second synchronization, the first one measured an offset of {:.4} seconds from {}",
+            first.offset, first.ntp_server
+        )));
+    }
+
+    let second = time(server).await.with_context(|| {
+        format!(
+            "the second synchronization failed, the first one measured an offset of {:.4} seconds from {}",
+            first.offset, first.ntp_server
+        )
+    })?;
+
+    // The rate is computed over the time actually elapsed between the two
+    // measurements, which the second synchronization's delay adds to
+    let elapsed = first_at.elapsed();
+
+    Ok(Drift {
+        first_offset: first.offset,
+        second_offset: second.offset,
+        elapsed,
+        ppm: drift_ppm(first.offset, second.offset, elapsed),
+        ntp_server: second.ntp_server,
+    })
+}
+
+/// Computes the drift rate, in parts per million, of a clock whose offset
+/// went from the first to the second one, in seconds, over the elapsed time.
+fn drift_ppm(first_offset: f64, second_offset: f64, elapsed: Duration) -> f64 {
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+
+    (second_offset - first_offset) / elapsed.as_secs_f64() * 1_000_000.0
+}
+
+/// The drift rate of the system clock, as measured by two synchronizations
+/// with an NTP server.
+///
+/// A positive rate means the system clock runs slower than the server's,
+/// the offset to apply to it growing over time.
+#[derive(Deserialize, Serialize)]
+pub struct Drift {
+    /// The offset measured by the first synchronization, in seconds.
+    first_offset: f64,
+
+    /// The offset measured by the second synchronization, in seconds.
+    second_offset: f64,
+
+    /// The time elapsed between the two synchronizations.
+    #[serde(rename = "elapsed_secs", with = "crate::serde_duration")]
+    elapsed: Duration,
+
+    /// The drift rate of the system clock, in parts per million.
+    ppm: f64,
+
+    /// The NTP server the second synchronization reached.
+    ntp_server: String,
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        // A drift of up to 100 ppm, that is about 8.6 seconds a day, is
+        // typical of an undisciplined quartz clock
+        let ppm = format!("{:+.2} ppm", self.ppm);
+        let ppm = match self.ppm.abs() {
+            abs if abs > 500.0 => ppm.color(palette.critical),
+            abs if abs > 100.0 => ppm.color(palette.warning),
+            _ => ppm.color(palette.good),
+        };
+
+        write!(
+            f,
+            "drift {} over {} ({:.4} → {:.4} seconds from {})",
+            ppm,
+            humantime::format_duration(Duration::from_secs(self.elapsed.as_secs_f64().round() as u64)),
+            self.first_offset,
+            self.second_offset,
+            self.ntp_server.color(palette.muted)
+        )
+    }
+}
+
 /// Returns the system date and time.
-pub async fn datetime() -> Result<Datetime> {
+///
+/// The time is synchronized with the first of the NTP servers to respond,
+/// the provided server being tried before the default ones.
+pub async fn datetime(server: Option<&str>) -> Result<Datetime> {
     let date = date().await?;
-    let time = time().await?;
+    let time = time(server).await?.ntp_details(false);
 
-    Ok(Datetime { date, time })
+    Ok(Datetime { date, time, oneline: false })
 }
 
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Datetime {
     date: Date,
     time: Time,
+
+    /// Whether to display the date and time on a single compact line.
+    #[serde(skip)]
+    oneline: bool,
+}
+
+impl Datetime {
+    /// Sets whether the date and time are displayed on a single compact line,
+    /// such as `Sat 8 Apr 2023 20:20:02 +02:00`, without the clock offset.
+    pub fn oneline(mut self, oneline: bool) -> Self {
+        self.oneline = oneline;
+        self
+    }
+
+    /// Sets whether the offset from the NTP server's clock is omitted from
+    /// the text output. It is still serialized.
+    pub fn no_offset(mut self, no_offset: bool) -> Self {
+        self.time = self.time.no_offset(no_offset);
+        self
+    }
 }
 
 impl Display for Datetime {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.oneline {
+            return write!(
+                f,
+                "{:.3} {} {:.3} {} {:02}:{:02}:{:02} {}",
+                self.date.day_name,
+                self.date.day_number,
+                self.date.month_name,
+                self.date.year,
+                self.time.hour,
+                self.time.minute,
+                self.time.second,
+                self.time.timezone
+            );
+        }
+
         write!(f, "{}", self.date)?;
         write!(f, "\n{}", self.time)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn synchronize_with_first_tries_the_next_server_when_one_fails() {
+        let servers = ["unreachable.example", "time.example"];
+
+        let t = synchronize_with_first(&servers, Duration::from_millis(200), |server| async move {
+            match server {
+                "time.example" => {
+                    let mut t = Time::from(fixed_datetime());
+                    t.offset = 0.25;
+                    Ok(t)
+                }
+                _ => Err(anyhow::anyhow!("connection refused")),
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(t.ntp_server, "time.example");
+        assert_eq!(t.offset(), 0.25);
+        assert_eq!((t.hour, t.minute, t.second), (20, 20, 2));
+    }
+
+    #[tokio::test]
+    async fn synchronize_with_first_reports_every_failure() {
+        let servers = ["unreachable.example", "slow.example"];
+
+        let err = synchronize_with_first(&servers, Duration::from_millis(50), |server| async move {
+            match server {
+                "slow.example" => std::future::pending().await,
+                _ => Err(anyhow::anyhow!("connection refused")),
+            }
+        })
+        .await
+        .err()
+        .unwrap()
+        .to_string();
+
+        assert!(err.contains("unreachable.example: connection refused"), "{}", err);
+        assert!(err.contains("slow.example: timed out"), "{}", err);
+    }
+
+    #[test]
+    fn drift_ppm_scales_the_offset_change_to_a_million() {
+        // Gaining 1ms over 1000s is drifting 1 part per million
+        assert!((drift_ppm(0.010, 0.011, Duration::from_secs(1000)) - 1.0).abs() < 1e-9);
+        assert!((drift_ppm(0.011, 0.010, Duration::from_secs(1000)) + 1.0).abs() < 1e-9);
+        assert_eq!(drift_ppm(0.010, 0.010, Duration::from_secs(60)), 0.0);
+        assert_eq!(drift_ppm(0.010, 0.020, Duration::ZERO), 0.0);
+    }
+
+    fn fixed_datetime() -> DateTime<Local> {
+        use chrono::TimeZone;
+
+        Local.with_ymd_and_hms(2023, 4, 8, 20, 20, 2).single().unwrap()
+    }
+
+    #[test]
+    fn datetime_oneline_fits_on_a_single_line() {
+        let now = fixed_datetime();
+        let datetime = Datetime { date: Date::from(now), time: Time::from(now), oneline: false }.oneline(true);
+
+        let rendered = datetime.to_string();
+        assert!(!rendered.contains('\n'), "{:?}", rendered);
+        assert!(rendered.starts_with("Sat 8 Apr 2023 20:20:02 "), "{:?}", rendered);
+    }
+
+    #[test]
+    fn date_holds_the_iso_date_ordinal_and_weekday() {
+        let date = serde_json::to_value(Date::from(fixed_datetime())).unwrap();
+
+        assert_eq!(date["iso_date"], "2023-04-08");
+        assert_eq!(date["ordinal"], 98);
+        assert_eq!(date["weekday_number"], 6);
+        assert_eq!(date["week_number"], 14);
+    }
+}
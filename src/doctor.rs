@@ -0,0 +1,147 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use colored::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cancel::CancellationToken;
+use crate::theme;
+use crate::{datetime, network, storage, system};
+
+/// The maximum duration a single diagnostic is allowed to run for.
+const DIAGNOSTIC_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs a battery of diagnostics, checking which capabilities of the tool
+/// work on this system.
+///
+/// Diagnostics run concurrently, each bounded by a timeout. Critical
+/// diagnostics check local capabilities the tool is expected to support
+/// everywhere, while the others depend on the network, privileges, or
+/// platform support.
+pub async fn diagnose() -> Diagnostics {
+    // The diagnostics are bounded by a timeout instead of observing Ctrl-C
+    let cancellation = CancellationToken::new();
+    let (cpu, ram, disks, disk_io, interfaces, mac_addresses, dns_servers, dns_resolution, ntp, users, arp) = tokio::join!(
+        run("cpu", true, system::cpus(false, false)),
+        run("ram", true, system::ram(false)),
+        run("disks", true, storage::list_disks(false)),
+        run("disk io", false, storage::disks_io(Duration::from_millis(100), &cancellation)),
+        run("interfaces", true, network::interfaces(false)),
+        run("mac addresses", false, async {
+            let interfaces = network::interfaces(false).await?;
+            if !interfaces.iter().any(|interface| interface.mac().is_some()) {
+                anyhow::bail!("no network interface exposes a MAC address");
+            }
+            Ok(())
+        }),
+        run("dns servers", true, async {
+            if network::list_dns_servers().await?.is_empty() {
+                anyhow::bail!("no DNS server is configured");
+            }
+            Ok(())
+        }),
+        run(
+            "dns resolution",
+            false,
+            network::query_public_ip(network::OPENDNS_SERVER_HOST, network::DNS_DEFAULT_PORT)
+        ),
+        run("ntp", false, datetime::time(None)),
+        run("logged-in users", false, system::users()),
+        run("arp cache", false, network::neighbors()),
+    );
+
+    Diagnostics(vec![
+        cpu,
+        ram,
+        disks,
+        disk_io,
+        interfaces,
+        mac_addresses,
+        dns_servers,
+        dns_resolution,
+        ntp,
+        users,
+        arp,
+    ])
+}
+
+/// Runs a single diagnostic, bounded by the diagnostic timeout.
+async fn run<T>(name: &str, critical: bool, check: impl Future<Output = Result<T>>) -> Diagnostic {
+    let reason = match tokio::time::timeout(DIAGNOSTIC_TIMEOUT, check).await {
+        Ok(Ok(_)) => None,
+        Ok(Err(err)) => Some(format!("{:#}", err)),
+        Err(_) => Some(format!("timed out after {}s", DIAGNOSTIC_TIMEOUT.as_secs())),
+    };
+
+    Diagnostic {
+        name: name.to_string(),
+        critical,
+        status: if reason.is_none() { "ok" } else { "fail" }.to_string(),
+        reason,
+    }
+}
+
+/// The outcome of a diagnostic.
+#[derive(Deserialize, Serialize)]
+pub struct Diagnostic {
+    /// The name of the capability checked by the diagnostic.
+    pub name: String,
+
+    /// Whether the capability is expected to be supported everywhere.
+    pub critical: bool,
+
+    /// The status of the diagnostic: ok or fail.
+    pub status: String,
+
+    /// The reason the diagnostic failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Diagnostic {
+    fn failed(&self) -> bool {
+        self.reason.is_some()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        match &self.reason {
+            None => write!(f, "{} {}", "✔".color(palette.good), self.name),
+            Some(reason) => {
+                let color = if self.critical { palette.critical } else { palette.warning };
+                write!(f, "{} {}: {}", "✘".color(color), self.name.bold(), reason)
+            }
+        }
+    }
+}
+
+/// The outcomes of the diagnostics, in the order they were listed.
+#[derive(Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    /// Returns whether any critical diagnostic failed.
+    pub fn critical_failure(&self) -> bool {
+        self.0.iter().any(|diagnostic| diagnostic.critical && diagnostic.failed())
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.0
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join("\n")
+        )
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::Display;
+use std::io::ErrorKind;
+
+use serde::Serialize;
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+
+/// The category of a command's failure.
+///
+/// Its serialized form is a stable code automation can branch on, rather
+/// than matching the error's message, which is free to change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// A remote host could not be reached, or did not respond in time.
+    NetworkUnreachable,
+
+    /// A DNS query failed, or its response could not be used.
+    DnsFailure,
+
+    /// No NTP server responded.
+    NtpFailure,
+
+    /// The command lacks the privileges it needs.
+    PermissionDenied,
+
+    /// The command is not supported on this platform.
+    UnsupportedPlatform,
+
+    /// A value, such as a captured result or an address, could not be parsed.
+    ParseError,
+
+    /// A requested resource, such as a file or network interface, does not exist.
+    NotFound,
+
+    /// The command was interrupted with Ctrl-C before it gathered a result.
+    Interrupted,
+
+    /// Any other failure.
+    Unknown,
+}
+
+impl ErrorCode {
+    /// Returns the exit code of the failure, following the conventions of
+    /// BSD's sysexits.h. Interrupted commands exit with 130, as shells report
+    /// processes killed by SIGINT, and uncategorized failures exit with 1.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCode::ParseError => 65,
+            ErrorCode::NotFound => 66,
+            ErrorCode::DnsFailure => 68,
+            ErrorCode::NetworkUnreachable => 69,
+            ErrorCode::UnsupportedPlatform => 71,
+            ErrorCode::NtpFailure => 75,
+            ErrorCode::PermissionDenied => 77,
+            ErrorCode::Interrupted => 130,
+            ErrorCode::Unknown => 1,
+        }
+    }
+}
+
+/// An error whose category is known where it is raised, rather than derived
+/// from its source, such as a platform lacking support for a command.
+#[derive(Debug)]
+pub struct Categorized {
+    code: ErrorCode,
+    message: String,
+}
+
+impl Display for Categorized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Categorized {}
+
+/// Creates an error of the provided category.
+pub fn categorized(code: ErrorCode, message: impl Into<String>) -> anyhow::Error {
+    anyhow::Error::new(Categorized {
+        code,
+        message: message.into(),
+    })
+}
+
+/// Categorizes an error from its sources, the outermost categorizable one
+/// taking precedence.
+pub fn classify_error(err: &anyhow::Error) -> ErrorCode {
+    err.chain()
+        .find_map(|source| {
+            if let Some(err) = source.downcast_ref::<Categorized>() {
+                return Some(err.code);
+            }
+            if let Some(err) = source.downcast_ref::<std::io::Error>() {
+                return classify_io_error(err);
+            }
+            if let Some(err) = source.downcast_ref::<ResolveError>() {
+                return Some(match err.kind() {
+                    ResolveErrorKind::Io(err) => classify_io_error(err).unwrap_or(ErrorCode::DnsFailure),
+                    ResolveErrorKind::Timeout | ResolveErrorKind::NoConnections => ErrorCode::NetworkUnreachable,
+                    _ => ErrorCode::DnsFailure,
+                });
+            }
+            if source.is::<trust_dns_proto::error::ProtoError>() {
+                return Some(ErrorCode::DnsFailure);
+            }
+            if let Some(ureq::Error::Transport(_)) = source.downcast_ref::<ureq::Error>() {
+                return Some(ErrorCode::NetworkUnreachable);
+            }
+            if source.is::<serde_json::Error>()
+                || source.is::<std::net::AddrParseError>()
+                || source.is::<std::num::ParseIntError>()
+                || source.is::<std::num::ParseFloatError>()
+            {
+                return Some(ErrorCode::ParseError);
+            }
+
+            None
+        })
+        .unwrap_or(ErrorCode::Unknown)
+}
+
+/// The OS error codes of an unreachable network or host. They are matched
+/// on directly, as the io::ErrorKind variants describing them need Rust 1.83.
+#[cfg(unix)]
+const UNREACHABLE_OS_ERRORS: [i32; 2] = [libc::ENETUNREACH, libc::EHOSTUNREACH];
+
+/// The OS error codes of an unreachable network or host: WSAENETUNREACH and
+/// WSAEHOSTUNREACH.
+#[cfg(windows)]
+const UNREACHABLE_OS_ERRORS: [i32; 2] = [10051, 10065];
+
+#[cfg(not(any(unix, windows)))]
+const UNREACHABLE_OS_ERRORS: [i32; 0] = [];
+
+/// Categorizes an IO error from its kind, if it is a categorizable one.
+fn classify_io_error(err: &std::io::Error) -> Option<ErrorCode> {
+    if let Some(code) = err.raw_os_error() {
+        if UNREACHABLE_OS_ERRORS.contains(&code) {
+            return Some(ErrorCode::NetworkUnreachable);
+        }
+    }
+
+    match err.kind() {
+        ErrorKind::PermissionDenied => Some(ErrorCode::PermissionDenied),
+        ErrorKind::NotFound => Some(ErrorCode::NotFound),
+        ErrorKind::Unsupported => Some(ErrorCode::UnsupportedPlatform),
+        ErrorKind::InvalidData => Some(ErrorCode::ParseError),
+        ErrorKind::ConnectionRefused
+        | ErrorKind::ConnectionReset
+        | ErrorKind::TimedOut
+        | ErrorKind::WouldBlock => Some(ErrorCode::NetworkUnreachable),
+        _ => None,
+    }
+}
+
+/// The failure of a command, as rendered by the JSON output format:
+/// `{"error": {"code": "dns_failure", "message": "..."}}`.
+#[derive(Serialize)]
+pub struct ErrorReport {
+    error: ErrorDetails,
+}
+
+#[derive(Serialize)]
+struct ErrorDetails {
+    code: ErrorCode,
+    message: String,
+}
+
+impl ErrorReport {
+    pub fn new(err: &anyhow::Error) -> Self {
+        ErrorReport {
+            error: ErrorDetails {
+                code: classify_error(err),
+                message: format!("{:#}", err),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(any(unix, windows))]
+    fn classify_io_error_detects_unreachable_networks_and_hosts() {
+        for code in UNREACHABLE_OS_ERRORS {
+            let err = std::io::Error::from_raw_os_error(code);
+            assert_eq!(classify_io_error(&err), Some(ErrorCode::NetworkUnreachable), "{}", err);
+        }
+
+        let refused = std::io::Error::from(ErrorKind::ConnectionRefused);
+        assert_eq!(classify_io_error(&refused), Some(ErrorCode::NetworkUnreachable));
+        assert_eq!(classify_io_error(&std::io::Error::from(ErrorKind::NotFound)), Some(ErrorCode::NotFound));
+    }
+}
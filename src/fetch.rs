@@ -0,0 +1,194 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use sysinfo::{System, SystemExt};
+use tokio::task::spawn_blocking;
+use unicode_width::UnicodeWidthStr;
+
+use crate::format::{human_readable_size, pad_right, Bytes};
+use crate::system;
+use crate::theme;
+
+/// Collects a curated summary of the system, as neofetch does: its
+/// hostname, OS, kernel, architecture, virtualization, uptime, CPU, RAM
+/// and shell.
+///
+/// The collectors run concurrently. When `logo` is set, the text output
+/// is preceded by an ASCII logo of the OS family, if there is one for it.
+pub async fn system_info(logo: bool) -> Result<SystemInfo> {
+    let (os, architecture, virtualization, uptime, cpu, ram, kernel) = tokio::join!(
+        system::os(),
+        system::architecture(false),
+        system::virtualization(),
+        system::uptime(),
+        system::cpus(false, false),
+        system::ram(false),
+        spawn_blocking(|| System::new().kernel_version()),
+    );
+    let (uptime, cpu, ram) = (uptime?, cpu?, ram?);
+
+    Ok(SystemInfo {
+        username: whoami::username(),
+        hostname: whoami::hostname(),
+        os: os?.distro,
+        architecture: architecture?.arch,
+        virtualization: virtualization?,
+        kernel: kernel?,
+        uptime: uptime.uptime,
+        boot_time: Some(uptime.boot_time),
+        cpu: cpu.brand,
+        cpu_cores: cpu.core_count,
+        total_ram: ram.total,
+        used_ram: ram.used,
+        shell: login_shell(),
+        logo,
+    })
+}
+
+/// Returns the name of the user's login shell, such as zsh, from the
+/// `SHELL` environment variable, or from `ComSpec` on Windows.
+fn login_shell() -> Option<String> {
+    let shell = std::env::var_os("SHELL").or_else(|| std::env::var_os("ComSpec"))?;
+
+    std::path::Path::new(&shell)
+        .file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .filter(|name| !name.is_empty())
+}
+
+/// A curated summary of the system.
+#[derive(Deserialize, Serialize)]
+pub struct SystemInfo {
+    pub username: String,
+
+    pub hostname: String,
+
+    // The full distribution string, such as Ubuntu 22.04.3 LTS
+    pub os: String,
+
+    // The kernel's version, if the system reports it
+    pub kernel: Option<String>,
+
+    // The architecture's name, such as x86_64
+    pub architecture: String,
+
+    // Whether the system runs inside a container or a virtual machine
+    #[serde(default)]
+    pub virtualization: system::Virtualization,
+
+    #[serde(rename = "uptime_secs", with = "crate::serde_duration")]
+    pub uptime: Duration,
+
+    // The time the system booted at, serialized in RFC 3339
+    #[serde(default)]
+    pub boot_time: Option<DateTime<Local>>,
+
+    // The CPU's brand
+    pub cpu: String,
+
+    // The CPU's number of logical cores
+    pub cpu_cores: usize,
+
+    #[serde(rename = "total_ram_bytes")]
+    pub total_ram: Bytes,
+
+    #[serde(rename = "used_ram_bytes")]
+    pub used_ram: Bytes,
+
+    // The name of the user's login shell, such as zsh, if it is known
+    pub shell: Option<String>,
+
+    // Whether to display the OS family's logo in the text output
+    #[serde(skip)]
+    logo: bool,
+}
+
+/// The ASCII logos of the OS families, displayed along with the summary.
+const LINUX_LOGO: &[&str] = &[
+    "    .--.    ",
+    "   |o_o |   ",
+    "   |:_/ |   ",
+    "  //   \\ \\  ",
+    " (|     | ) ",
+    "/'\\_   _/`\\ ",
+    "\\___)=(___/ ",
+];
+
+const MACOS_LOGO: &[&str] = &[
+    "       .:'   ",
+    "   __ :'__   ",
+    ".'`  `-'  ``.",
+    ":          .-'",
+    ":         :  ",
+    " :         `-;",
+    "  `.__.-.__.'",
+];
+
+const WINDOWS_LOGO: &[&str] = &[
+    "####  ####",
+    "####  ####",
+    "          ",
+    "####  ####",
+    "####  ####",
+];
+
+/// Returns the ASCII logo of the OS family the binary runs on, if there is one.
+fn os_logo() -> Option<&'static [&'static str]> {
+    match std::env::consts::OS {
+        "linux" => Some(LINUX_LOGO),
+        "macos" => Some(MACOS_LOGO),
+        "windows" => Some(WINDOWS_LOGO),
+        _ => None,
+    }
+}
+
+impl Display for SystemInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        let used_percent = if self.total_ram.0 == 0 { 0.0 } else { self.used_ram.0 as f64 / self.total_ram.0 as f64 * 100.0 };
+        let unknown = || "unknown".to_string();
+
+        let rows = [
+            ("os", self.os.clone()),
+            ("kernel", self.kernel.clone().unwrap_or_else(unknown)),
+            ("architecture", self.architecture.clone()),
+            ("virtualization", self.virtualization.to_string()),
+            ("uptime", system::format_uptime(self.uptime)),
+            ("cpu", format!("{} ({} cores)", self.cpu, self.cpu_cores)),
+            ("ram", format!("{} / {} ({:.1}%)", human_readable_size(self.used_ram.0), human_readable_size(self.total_ram.0), used_percent)),
+            ("shell", self.shell.clone().unwrap_or_else(unknown)),
+        ];
+
+        // The labels are padded, so that the values line up
+        let title = format!("{}@{}", self.username, self.hostname);
+        let label_width = rows.iter().map(|(label, _)| label.width()).max().unwrap_or(0);
+        let mut lines = vec![
+            title.color(palette.name).bold().to_string(),
+            "-".repeat(title.width()),
+        ];
+        lines.extend(rows.iter().map(|(label, value)| format!("{}  {}", pad_right(label, label_width).color(palette.name).bold(), value)));
+
+        let logo = if self.logo { os_logo().unwrap_or_default() } else { &[] };
+        let logo_width = logo.iter().map(|line| line.width()).max().unwrap_or(0);
+
+        let output = (0..lines.len().max(logo.len()))
+            .map(|index| {
+                let line = lines.get(index).map(String::as_str).unwrap_or_default();
+                if logo.is_empty() {
+                    return line.to_string();
+                }
+
+                let logo_line = pad_right(logo.get(index).copied().unwrap_or_default(), logo_width);
+                format!("{}   {}", logo_line.color(palette.accent), line)
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        write!(f, "{}", output)
+    }
+}
@@ -1,3 +1,9 @@
+use std::cell::Cell;
+
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use unicode_width::UnicodeWidthStr;
+
 /// Convert bytes to human readable size
 pub fn human_readable_size(bytes: u64) -> String {
     const KILO: u64 = 1024;
@@ -14,4 +20,216 @@ pub fn human_readable_size(bytes: u64) -> String {
         _ if bytes < PETA => format!("{:.2} TiB", bytes as f64 / TERA as f64),
         _ => format!("{:.2} PiB", bytes as f64 / PETA as f64),
     }
-}
\ No newline at end of file
+}
+
+thread_local! {
+    static HUMAN_BYTES: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets whether byte sizes are serialized along with their human readable form.
+///
+/// The setting applies to the calling thread, which the results are output
+/// from, and should be set before any result is serialized.
+pub fn set_human_bytes(enabled: bool) {
+    HUMAN_BYTES.with(|human_bytes| human_bytes.set(enabled));
+}
+
+/// A size in bytes
+///
+/// Every field holding a byte size is of this type, so that sizes are
+/// serialized the same way across results: as a plain integer, or, when
+/// human readable byte sizes are enabled, as an object holding both the
+/// integer and its human readable form:
+/// `{ "bytes": 17179869184, "human": "16.00 GiB" }`. Both forms deserialize.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bytes(pub u64);
+
+impl From<u64> for Bytes {
+    fn from(bytes: u64) -> Self {
+        Bytes(bytes)
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !HUMAN_BYTES.with(Cell::get) {
+            return serializer.serialize_u64(self.0);
+        }
+
+        let mut state = serializer.serialize_struct("Bytes", 2)?;
+        state.serialize_field("bytes", &self.0)?;
+        state.serialize_field("human", &human_readable_size(self.0))?;
+        state.end()
+    }
+}
+
+/// A size in bytes, as serialized with or without its human readable form
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedBytes<T> {
+    Plain(T),
+    Human { bytes: T },
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match SerializedBytes::deserialize(deserializer)? {
+            SerializedBytes::Plain(bytes) | SerializedBytes::Human { bytes } => Ok(Bytes(bytes)),
+        }
+    }
+}
+
+/// A difference between two sizes in bytes, negative if the size shrank
+///
+/// It is serialized like `Bytes`, its human readable form being signed:
+/// `{ "bytes": -1048576, "human": "-1.00 MiB" }`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SignedBytes(pub i64);
+
+impl Serialize for SignedBytes {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !HUMAN_BYTES.with(Cell::get) {
+            return serializer.serialize_i64(self.0);
+        }
+
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let mut state = serializer.serialize_struct("SignedBytes", 2)?;
+        state.serialize_field("bytes", &self.0)?;
+        state.serialize_field("human", &format!("{}{}", sign, human_readable_size(self.0.unsigned_abs())))?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for SignedBytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match SerializedBytes::deserialize(deserializer)? {
+            SerializedBytes::Plain(bytes) | SerializedBytes::Human { bytes } => Ok(SignedBytes(bytes)),
+        }
+    }
+}
+
+/// The style used to render large counts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CountStyle {
+    /// Every digit is kept, grouped by thousands: 1,500,000
+    Separated,
+
+    /// The count is abbreviated with a K, M or B suffix: 1.50M
+    Abbreviated,
+}
+
+/// Convert a count to a human readable number
+pub fn human_readable_count(count: u64, style: CountStyle) -> String {
+    const THOUSAND: u64 = 1000;
+    const MILLION: u64 = 1000 * THOUSAND;
+    const BILLION: u64 = 1000 * MILLION;
+
+    match style {
+        CountStyle::Separated => {
+            // The digits are grouped by three from the right, and being ASCII,
+            // every group is valid UTF-8
+            let digits = count.to_string();
+            digits
+                .as_bytes()
+                .rchunks(3)
+                .rev()
+                .map(|group| std::str::from_utf8(group).unwrap())
+                .collect::<Vec<&str>>()
+                .join(",")
+        }
+        CountStyle::Abbreviated => {
+            // The count is scaled as it is displayed, rounded to two decimals,
+            // so that 999,999 rolls over to 1.00M rather than being 1000.00K
+            let scaled = |unit: u64| (count as f64 / unit as f64 * 100.0).round() / 100.0;
+
+            match count {
+                _ if count < THOUSAND => count.to_string(),
+                _ if scaled(THOUSAND) < 1000.0 => format!("{:.2}K", scaled(THOUSAND)),
+                _ if scaled(MILLION) < 1000.0 => format!("{:.2}M", scaled(MILLION)),
+                _ => format!("{:.2}B", scaled(BILLION)),
+            }
+        }
+    }
+}
+
+/// Pad a value with trailing spaces up to the provided display width
+///
+/// The width is measured on the plain value, before any color is applied,
+/// so that columns line up whether or not colors are enabled.
+pub fn pad_right(value: &str, width: usize) -> String {
+    format!("{}{}", value, " ".repeat(width.saturating_sub(value.width())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_readable_count_separates_thousands() {
+        assert_eq!(human_readable_count(999, CountStyle::Separated), "999");
+        assert_eq!(human_readable_count(1_000, CountStyle::Separated), "1,000");
+        assert_eq!(human_readable_count(1_500_000, CountStyle::Separated), "1,500,000");
+    }
+
+    #[test]
+    fn human_readable_count_abbreviates() {
+        assert_eq!(human_readable_count(999, CountStyle::Abbreviated), "999");
+        assert_eq!(human_readable_count(1_000, CountStyle::Abbreviated), "1.00K");
+        assert_eq!(human_readable_count(1_500_000, CountStyle::Abbreviated), "1.50M");
+        assert_eq!(human_readable_count(2_000_000_000, CountStyle::Abbreviated), "2.00B");
+    }
+
+    #[test]
+    fn human_readable_count_rolls_over_to_the_next_unit() {
+        assert_eq!(human_readable_count(999_994, CountStyle::Abbreviated), "999.99K");
+        assert_eq!(human_readable_count(999_999, CountStyle::Abbreviated), "1.00M");
+        assert_eq!(human_readable_count(999_999_999, CountStyle::Abbreviated), "1.00B");
+    }
+
+    #[test]
+    fn bytes_serialize_as_a_plain_integer_by_default() {
+        assert_eq!(serde_json::to_value(Bytes(2048)).unwrap(), serde_json::json!(2048));
+    }
+
+    #[test]
+    fn bytes_serialize_with_their_human_form_when_enabled() {
+        set_human_bytes(true);
+        let value = serde_json::to_value(Bytes(2048)).unwrap();
+        set_human_bytes(false);
+
+        assert_eq!(value, serde_json::json!({ "bytes": 2048, "human": "2.00 KiB" }));
+    }
+
+    #[test]
+    fn bytes_deserialize_from_both_forms() {
+        let plain: Bytes = serde_json::from_value(serde_json::json!(2048)).unwrap();
+        let human: Bytes = serde_json::from_value(serde_json::json!({ "bytes": 2048, "human": "2.00 KiB" })).unwrap();
+
+        assert_eq!(plain, Bytes(2048));
+        assert_eq!(human, Bytes(2048));
+    }
+
+    #[test]
+    fn signed_bytes_serialize_with_a_signed_human_form() {
+        set_human_bytes(true);
+        let shrank = serde_json::to_value(SignedBytes(-1024 * 1024)).unwrap();
+        let grew = serde_json::to_value(SignedBytes(2048)).unwrap();
+        set_human_bytes(false);
+
+        assert_eq!(shrank, serde_json::json!({ "bytes": -1048576, "human": "-1.00 MiB" }));
+        assert_eq!(grew, serde_json::json!({ "bytes": 2048, "human": "2.00 KiB" }));
+        assert_eq!(serde_json::to_value(SignedBytes(-2048)).unwrap(), serde_json::json!(-2048));
+    }
+}
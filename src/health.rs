@@ -0,0 +1,452 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use sysinfo::{CpuRefreshKind, RefreshKind, System, SystemExt};
+
+use crate::theme;
+use crate::{datetime, network, storage, system};
+
+/// The maximum duration measuring a single signal is allowed to take.
+const SIGNAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A signal the health of the system is assessed from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Signal {
+    Ram,
+    Disk,
+    Load,
+    Offset,
+}
+
+impl Signal {
+    /// Returns the signal's name, as used to override its thresholds.
+    fn name(self) -> &'static str {
+        match self {
+            Signal::Ram => "ram",
+            Signal::Disk => "disk",
+            Signal::Load => "load",
+            Signal::Offset => "offset",
+        }
+    }
+
+    /// Returns whether lower values of the signal are worse, as for the
+    /// free disk space, rather than higher ones.
+    fn lower_is_worse(self) -> bool {
+        self == Signal::Disk
+    }
+}
+
+/// The warning and critical thresholds of a signal.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Threshold {
+    pub warning: f64,
+    pub critical: f64,
+}
+
+impl Threshold {
+    /// Returns the status a value of the signal triggers, along with the
+    /// threshold it crossed, if any.
+    fn evaluate(self, signal: Signal, value: f64) -> (Status, Option<f64>) {
+        let crosses = |threshold: f64| if signal.lower_is_worse() { value <= threshold } else { value >= threshold };
+
+        if crosses(self.critical) {
+            (Status::Critical, Some(self.critical))
+        } else if crosses(self.warning) {
+            (Status::Warn, Some(self.warning))
+        } else {
+            (Status::Ok, None)
+        }
+    }
+}
+
+/// The thresholds of each signal.
+///
+/// The defaults match those of the nagios checks of the ram, disks and
+/// time commands: the RAM in use and the free disk space are percentages,
+/// the load is the five-minute load average per logical core, and the
+/// offset of the clock from NTP is in seconds.
+pub struct Thresholds {
+    pub ram: Threshold,
+    pub disk: Threshold,
+    pub load: Threshold,
+    pub offset: Threshold,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            ram: Threshold { warning: 70.0, critical: 90.0 },
+            disk: Threshold { warning: 20.0, critical: 10.0 },
+            load: Threshold { warning: 1.0, critical: 2.0 },
+            offset: Threshold { warning: 60.0, critical: 120.0 },
+        }
+    }
+}
+
+impl Thresholds {
+    /// Returns the default thresholds, with the provided ones overriding them.
+    pub fn with_overrides(overrides: &[ThresholdOverride]) -> Thresholds {
+        let mut thresholds = Thresholds::default();
+        for threshold_override in overrides {
+            *thresholds.get_mut(threshold_override.signal) = threshold_override.threshold;
+        }
+
+        thresholds
+    }
+
+    fn get(&self, signal: Signal) -> Threshold {
+        match signal {
+            Signal::Ram => self.ram,
+            Signal::Disk => self.disk,
+            Signal::Load => self.load,
+            Signal::Offset => self.offset,
+        }
+    }
+
+    fn get_mut(&mut self, signal: Signal) -> &mut Threshold {
+        match signal {
+            Signal::Ram => &mut self.ram,
+            Signal::Disk => &mut self.disk,
+            Signal::Load => &mut self.load,
+            Signal::Offset => &mut self.offset,
+        }
+    }
+}
+
+/// ThresholdOverride overrides the thresholds of a signal, expressed as
+/// `signal=warning,critical`, such as `ram=80,95`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThresholdOverride {
+    pub signal: Signal,
+    pub threshold: Threshold,
+}
+
+impl FromStr for ThresholdOverride {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let (name, values) = spec
+            .split_once('=')
+            .ok_or_else(|| anyhow!("the threshold {:?} is not of the form signal=warning,critical", spec))?;
+
+        let signal = [Signal::Ram, Signal::Disk, Signal::Load, Signal::Offset]
+            .into_iter()
+            .find(|signal| signal.name() == name.trim().to_lowercase())
+            .ok_or_else(|| anyhow!("unknown signal {:?}; expected one of ram, disk, load or offset", name.trim()))?;
+
+        let (warning, critical) = values
+            .split_once(',')
+            .ok_or_else(|| anyhow!("the threshold {:?} is not of the form signal=warning,critical", spec))?;
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| anyhow!("the threshold value {:?} is not a number", value.trim()))
+        };
+        let threshold = Threshold {
+            warning: parse(warning)?,
+            critical: parse(critical)?,
+        };
+
+        let ordered = if signal.lower_is_worse() {
+            threshold.critical <= threshold.warning
+        } else {
+            threshold.warning <= threshold.critical
+        };
+        if !ordered {
+            bail!(
+                "the {} warning threshold must be {} its critical threshold",
+                signal.name(),
+                if signal.lower_is_worse() { "above" } else { "below" }
+            );
+        }
+
+        Ok(ThresholdOverride { signal, threshold })
+    }
+}
+
+/// Assesses the health of the system, from its RAM in use, its fullest
+/// disk, its load, its clock's offset from NTP, and its connectivity.
+///
+/// Signals are measured concurrently, each bounded by a timeout. A signal
+/// which cannot be measured warns, as does a lack of connectivity, which
+/// is checked by looking up the public IP address. Read-only and pseudo
+/// filesystems are left out of the disks, and a system without any other
+/// disk, such as a container, is not assessed on its disks.
+pub async fn assess(thresholds: &Thresholds) -> Health {
+    let (ram, disk, load, offset, connectivity) = tokio::join!(
+        measure(async { Ok(system::ram(false).await?.used_percent()) }),
+        measure(fullest_disk()),
+        measure(load_per_core()),
+        measure(async { Ok(datetime::time(None).await?.offset().abs()) }),
+        measure(network::query_public_ip(network::OPENDNS_SERVER_HOST, network::DNS_DEFAULT_PORT)),
+    );
+
+    let mut status = Status::Ok;
+    let mut reasons = Vec::new();
+    let mut assess_signal = |signal: Signal, measured: &std::result::Result<f64, String>, describe: &dyn Fn(f64) -> String| {
+        match measured {
+            Ok(value) => {
+                if let (signal_status, Some(threshold)) = thresholds.get(signal).evaluate(signal, *value) {
+                    status = status.max(signal_status);
+                    reasons.push(Reason {
+                        status: signal_status,
+                        message: format!(
+                            "{}, {} the {} threshold of {}",
+                            describe(*value),
+                            if signal.lower_is_worse() { "below" } else { "over" },
+                            signal_status,
+                            threshold
+                        ),
+                    });
+                }
+            }
+            Err(err) => {
+                status = status.max(Status::Warn);
+                reasons.push(Reason {
+                    status: Status::Warn,
+                    message: format!("{} could not be measured: {}", signal.name(), err),
+                });
+            }
+        }
+    };
+
+    assess_signal(Signal::Ram, &ram, &|value| format!("ram {:.1}% used", value));
+    match &disk {
+        Ok(Some((mount_point, free_percent))) => {
+            assess_signal(Signal::Disk, &Ok(*free_percent), &|value| format!("disk {:.1}% free on {}", value, mount_point))
+        }
+        Ok(None) => {}
+        Err(err) => assess_signal(Signal::Disk, &Err(err.clone()), &|_| String::new()),
+    }
+    assess_signal(Signal::Load, &load, &|value| format!("load {:.2} per core", value));
+    assess_signal(Signal::Offset, &offset, &|value| format!("clock offset {:.4}s", value));
+
+    if let Err(err) = &connectivity {
+        status = status.max(Status::Warn);
+        reasons.push(Reason {
+            status: Status::Warn,
+            message: format!("offline: the public IP address could not be looked up: {}", err),
+        });
+    }
+
+    let disk = disk.ok().flatten();
+
+    Health {
+        status,
+        reasons,
+        metrics: HealthMetrics {
+            ram_used_percent: ram.ok().map(round_to_tenth),
+            disk_min_free_percent: disk.as_ref().map(|(_, free_percent)| round_to_tenth(*free_percent)),
+            disk_min_free_mount_point: disk.map(|(mount_point, _)| mount_point),
+            load_per_core: load.ok().map(|load| (load * 100.0).round() / 100.0),
+            clock_offset_secs: offset.ok().map(|offset| (offset * 10000.0).round() / 10000.0),
+            online: connectivity.is_ok(),
+        },
+    }
+}
+
+/// Measures a signal, bounded by the signal timeout, and returns the reason
+/// it could not be measured otherwise.
+async fn measure<T>(signal: impl Future<Output = Result<T>>) -> std::result::Result<T, String> {
+    match tokio::time::timeout(SIGNAL_TIMEOUT, signal).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(err)) => Err(format!("{:#}", err)),
+        Err(_) => Err(format!("timed out after {}s", SIGNAL_TIMEOUT.as_secs())),
+    }
+}
+
+/// Returns the mount point of the monitored disk with the least free space,
+/// relative to its capacity, along with its percentage of free space, or
+/// None when there is no monitored disk.
+async fn fullest_disk() -> Result<Option<(String, f64)>> {
+    Ok(storage::list_disks(false)
+        .await?
+        .into_iter()
+        .filter(storage::DiskInfo::is_monitored)
+        .map(|disk| (disk.mount_point.clone(), 100.0 - disk.used_percent()))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b)))
+}
+
+/// Returns the five-minute load average divided by the number of logical
+/// cores, which is only reported on Unix systems.
+async fn load_per_core() -> Result<f64> {
+    if !cfg!(unix) {
+        bail!("the load average is only reported on Unix systems");
+    }
+
+    let system = System::new_with_specifics(RefreshKind::new().with_cpu(CpuRefreshKind::new()));
+    let core_count = system.cpus().len().max(1);
+
+    Ok(system.load_average().five / core_count as f64)
+}
+
+fn round_to_tenth(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// The health status of the system, from the worst status of its signals.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warn,
+    Critical,
+}
+
+impl Status {
+    /// Returns the exit code of the status, as nagios checks do.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Status::Ok => 0,
+            Status::Warn => 1,
+            Status::Critical => 2,
+        }
+    }
+
+    fn color(self) -> Color {
+        let palette = theme::palette();
+
+        match self {
+            Status::Ok => palette.good,
+            Status::Warn => palette.warning,
+            Status::Critical => palette.critical,
+        }
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Ok => write!(f, "ok"),
+            Status::Warn => write!(f, "warn"),
+            Status::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A reason contributing to the health status of the system.
+#[derive(Deserialize, Serialize)]
+pub struct Reason {
+    /// The status the reason calls for: warn or critical.
+    pub status: Status,
+
+    /// The description of the reason, such as `ram 92.4% used, over the critical threshold of 90`.
+    pub message: String,
+}
+
+/// The raw metrics the health of the system is assessed from, each null
+/// when it could not be measured.
+#[derive(Deserialize, Serialize)]
+pub struct HealthMetrics {
+    /// The percentage of the installed RAM in use.
+    pub ram_used_percent: Option<f64>,
+
+    /// The percentage of free space of the fullest disk.
+    pub disk_min_free_percent: Option<f64>,
+
+    /// The mount point of the fullest disk.
+    pub disk_min_free_mount_point: Option<String>,
+
+    /// The five-minute load average per logical core.
+    pub load_per_core: Option<f64>,
+
+    /// The absolute offset of the system clock from NTP, in seconds.
+    pub clock_offset_secs: Option<f64>,
+
+    /// Whether the public IP address could be looked up.
+    pub online: bool,
+}
+
+/// The health of the system.
+#[derive(Deserialize, Serialize)]
+pub struct Health {
+    pub status: Status,
+
+    /// The reasons for the status, empty when the system is healthy.
+    pub reasons: Vec<Reason>,
+
+    pub metrics: HealthMetrics,
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let metrics = &self.metrics;
+        let measured = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+
+        write!(
+            f,
+            "{}: ram {}, disk {}, load {}, offset {}, {}",
+            self.status.to_string().color(self.status.color()).bold(),
+            measured(metrics.ram_used_percent.map(|value| format!("{:.1}% used", value))),
+            measured(metrics.disk_min_free_percent.map(|value| format!("{:.1}% free", value))),
+            measured(metrics.load_per_core.map(|value| format!("{:.2}/core", value))),
+            measured(metrics.clock_offset_secs.map(|value| format!("±{:.4}s", value))),
+            if metrics.online { "online" } else { "offline" },
+        )?;
+
+        for reason in &self.reasons {
+            write!(f, "\n  {} {}", "•".color(reason.status.color()), reason.message)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_override_parses_ordered_thresholds() {
+        assert_eq!(
+            "disk=20,10".parse::<ThresholdOverride>().unwrap(),
+            ThresholdOverride { signal: Signal::Disk, threshold: Threshold { warning: 20.0, critical: 10.0 } }
+        );
+        assert_eq!(
+            " RAM = 80 , 95 ".parse::<ThresholdOverride>().unwrap(),
+            ThresholdOverride { signal: Signal::Ram, threshold: Threshold { warning: 80.0, critical: 95.0 } }
+        );
+    }
+
+    #[test]
+    fn threshold_override_rejects_misordered_thresholds() {
+        let err = "disk=10,20".parse::<ThresholdOverride>().unwrap_err().to_string();
+        assert!(err.contains("must be above"), "{}", err);
+
+        let err = "ram=95,80".parse::<ThresholdOverride>().unwrap_err().to_string();
+        assert!(err.contains("must be below"), "{}", err);
+    }
+
+    #[test]
+    fn threshold_override_rejects_malformed_specs() {
+        for spec in ["ram", "ram=80", "ram=eighty,95", "ram=80,", "swap=80,95", ""] {
+            assert!(spec.parse::<ThresholdOverride>().is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn threshold_evaluate_when_higher_is_worse() {
+        let threshold = Threshold { warning: 70.0, critical: 90.0 };
+
+        assert_eq!(threshold.evaluate(Signal::Ram, 95.0), (Status::Critical, Some(90.0)));
+        assert_eq!(threshold.evaluate(Signal::Ram, 90.0), (Status::Critical, Some(90.0)));
+        assert_eq!(threshold.evaluate(Signal::Ram, 75.0), (Status::Warn, Some(70.0)));
+        assert_eq!(threshold.evaluate(Signal::Ram, 50.0), (Status::Ok, None));
+    }
+
+    #[test]
+    fn threshold_evaluate_when_lower_is_worse() {
+        let threshold = Threshold { warning: 20.0, critical: 10.0 };
+
+        assert_eq!(threshold.evaluate(Signal::Disk, 5.0), (Status::Critical, Some(10.0)));
+        assert_eq!(threshold.evaluate(Signal::Disk, 10.0), (Status::Critical, Some(10.0)));
+        assert_eq!(threshold.evaluate(Signal::Disk, 15.0), (Status::Warn, Some(20.0)));
+        assert_eq!(threshold.evaluate(Signal::Disk, 50.0), (Status::Ok, None));
+    }
+}
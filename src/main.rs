@@ -1,17 +1,29 @@
 use std::fmt::Display;
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use anyhow::{Result, Context};
 use clap::{Parser, Subcommand, ValueEnum};
 use human_panic::setup_panic;
 use serde::{Serialize, Serializer};
+use serde_json::Value;
 
+mod cancel;
 mod country;
 mod datetime;
+mod doctor;
+mod error;
+mod fetch;
 mod format;
+mod health;
+mod nagios;
 mod network;
 mod output;
+mod serde_duration;
 mod storage;
 mod system;
+mod theme;
 
 
 #[derive(Debug, Parser)]
@@ -22,8 +34,69 @@ pub struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    #[arg(short, long, visible_short_alias = 'o', value_enum, ignore_case = true, default_value_t = OutputFormat::Auto)]
     format: OutputFormat,
+
+    /// The output configuration as a single spec, such as 'json;pretty=false;fields=brand,frequency'.
+    /// Supported options are pretty, fields and color.
+    #[arg(long, value_name = "SPEC", conflicts_with = "format")]
+    output: Option<output::OutputSpec>,
+
+    /// Output JSON, as a shorthand for --format json
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Output a single field's bare value, such as used_percent or time.offset, and nothing else
+    #[arg(long, global = true, value_name = "FIELD")]
+    raw_field: Option<String>,
+
+    /// Round the numeric value of --raw-field to an integer, such as for shell arithmetic:
+    /// $(( $(my ram --raw-field used_percent --round) > 90 ))
+    #[arg(long, global = true, requires = "raw_field")]
+    round: bool,
+
+    #[arg(long, value_enum, default_value_t = theme::Theme::Dark)]
+    theme: theme::Theme,
+
+    /// The warning threshold of the nagios output format's check
+    #[arg(long)]
+    warn: Option<f64>,
+
+    /// The critical threshold of the nagios output format's check
+    #[arg(long)]
+    crit: Option<f64>,
+
+    /// Wrap the JSON output in an envelope holding its schema version
+    #[arg(long)]
+    schema_version: bool,
+
+    /// Serialize byte sizes along with their human readable form
+    #[arg(long)]
+    human_json: bool,
+
+    /// Suppress the output, only reporting the outcome through the exit code
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Omit the trailing newline from the text output of single-value results
+    #[arg(long)]
+    no_newline: bool,
+
+    /// Stamp the output with the time the result was captured at
+    #[arg(long)]
+    timestamp: bool,
+
+    /// Omit the fields holding null or an empty array from the structured output
+    #[arg(long)]
+    compact_json: bool,
+
+    /// Tag the structured output with the kind of result it holds, as a type field
+    #[arg(long)]
+    tagged: bool,
+
+    /// Never truncate the cells of tables to fit the terminal's width
+    #[arg(long)]
+    no_truncate: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -32,16 +105,63 @@ enum Commands {
     #[command(about = "Display your IP addresses")]
     #[command(long_about = "Find all IP addresses associated with your system, both local and external.\n\
     By default, it shows both public and local IP addresses.\n\
-    Use the --only flag to display one specific category.")]
+    Use the --only flag to display one specific category.\n\
+    The public IP address is looked up through DNS by default; use --method https to look it up\n\
+    over HTTPS instead, on networks filtering DNS queries.\n\
+    On hosts with several network interfaces, use the --all flag to list the local IP addresses\n\
+    of every interface, rather than the system's primary one.\n\
+    Use the --best-effort flag to still display the local IP addresses when the public IP address\n\
+    cannot be looked up, along with a warning.\n\
+    On Linux, use the --bind-interface option to send the public IP address' DNS query through a\n\
+    specific network interface.\n\
+    Use the --consensus flag along with --only public to query several resolvers (OpenDNS, Google\n\
+    and Cloudflare) concurrently, and display each one's answer along with the address most of\n\
+    them agree on; disagreeing answers are highlighted.\n\
+    Use the --source option to display the local IP address the system would send packets to a\n\
+    destination from, as picked by its routing table, which defaults to 8.8.8.8. No packet is sent.")]
     Ips {
         #[arg(long)]
         only: Option<network::IpCategory>,
+
+        #[arg(long, value_enum, default_value_t = network::PublicIpMethod::Dns)]
+        method: network::PublicIpMethod,
+
+        #[arg(long)]
+        all: bool,
+
+        #[arg(long)]
+        best_effort: bool,
+
+        #[arg(long, value_name = "INTERFACE")]
+        bind_interface: Option<String>,
+
+        #[arg(long, conflicts_with = "bind_interface")]
+        consensus: bool,
+
+        #[arg(
+            long,
+            value_name = "DESTINATION",
+            num_args = 0..=1,
+            default_missing_value = network::SOURCE_IP_DEFAULT_DESTINATION,
+            conflicts_with_all = ["only", "method", "all", "best_effort", "bind_interface", "consensus"]
+        )]
+        source: Option<std::net::IpAddr>,
     },
 
     #[command(name = "dns")]
     #[command(about = "Display your system's DNS servers")]
-    #[command(long_about = "Show the DNS servers configured on your system, listed in the order they are used.")]
-    Dns,
+    #[command(long_about = "Show the DNS servers configured on your system, listed in the order they are used.\n\
+    Use the --indexed flag to include each server's position in the resolution order in the\n\
+    structured output formats.\n\
+    Use the --count-only flag to only display the number of DNS servers configured; the command\n\
+    then exits with a nonzero status when there is none.")]
+    Dns {
+        #[arg(long)]
+        indexed: bool,
+
+        #[arg(long, conflicts_with = "indexed")]
+        count_only: bool,
+    },
 
     // #[command(arg_required_else_help = true)]
     #[command(name = "date")]
@@ -54,26 +174,83 @@ enum Commands {
     #[command(about = "Display your system's current time")]
     #[command(long_about = "Show the current time on your system, along with the offset from the central NTP\n\
     clock server, in a 24-hour human-readable format.\n
-    Example: 20:20:2 UTC +02:00 ±0.0672 seconds")]
-    Time,
+    Example: 20:20:2 UTC +02:00 ±0.0672 seconds\n\
+    Use the --no-offset flag to omit the offset from the text output.\n\
+    The time is synchronized with the first NTP server to respond among pool.ntp.org,\n\
+    time.cloudflare.com and time.google.com; use the --server option to try another one first.\n\
+    Use the --verbose flag to also display the round-trip delay to the NTP server.\n\
+    Use the --ntp-details flag to also report the NTP server's stratum, reference identifier, and\n\
+    leap second warning, which describe the quality of the time source.\n\
+    Use the --drift flag to synchronize twice, --interval apart, and display the drift rate of your\n\
+    system's clock in parts per million instead; interrupting it with Ctrl-C before the second\n\
+    synchronization reports the first offset, and exits with status 130.\n\
+    Example: drift +12.40 ppm over 10s (0.0123 → 0.0124 seconds from pool.ntp.org)")]
+    Time {
+        #[arg(long)]
+        no_offset: bool,
+
+        #[arg(short, long)]
+        verbose: bool,
+
+        #[arg(long)]
+        ntp_details: bool,
+
+        #[arg(long)]
+        server: Option<String>,
+
+        #[arg(long, conflicts_with_all = ["no_offset", "verbose", "ntp_details"])]
+        drift: bool,
+
+        #[arg(long, default_value = "10s", value_parser = humantime::parse_duration, requires = "drift")]
+        interval: Duration,
+    },
 
     #[command(name = "datetime")]
     #[command(about = "Display your system's current date and time")]
     #[command(long_about = "Show the current date and time on your system, along with the offset from\n\
     the central NTP clock server, in a human-readable format.\n\
-    Example: Saturday, 8 April, 2023, week 14 20:20:2 UTC +02:00 ±0.0684 seconds")]
-    Datetime,
+    Example: Saturday, 8 April, 2023, week 14 20:20:2 UTC +02:00 ±0.0684 seconds\n\
+    Use the --oneline flag to display them on a single compact line instead.\n\
+    Example: Sat 8 Apr 2023 20:20:02 +02:00\n\
+    Use the --no-offset flag to omit the offset from the text output.\n\
+    Use the --server option to synchronize with another NTP server first.")]
+    Datetime {
+        #[arg(long)]
+        oneline: bool,
+
+        #[arg(long)]
+        no_offset: bool,
+
+        #[arg(long)]
+        server: Option<String>,
+    },
 
     #[command(name = "hostname")]
     #[command(about = "Display your system's hostname")]
-    #[command(long_about = "Show the hostname assigned to your system.")]
-    Hostname,
+    #[command(long_about = "Show the hostname assigned to your system.\n\
+    Use the --fqdn flag to display its fully qualified domain name instead, as resolved from\n\
+    the system's local IP address. Note that resolving the FQDN may hit the network.\n\
+    Use the --aliases flag to display its canonical name along with the other names it is known\n\
+    by, as listed in the hosts file, instead.")]
+    Hostname {
+        #[arg(long)]
+        fqdn: bool,
+
+        #[arg(long, conflicts_with = "fqdn")]
+        aliases: bool,
+    },
 
     #[command(name = "username")]
     #[command(about = "Display your current system user's username")]
     #[command(long_about = "Show the username of the currently logged-in system user.")]
     Username,
 
+    #[command(name = "users")]
+    #[command(about = "Display the users logged in on your system")]
+    #[command(long_about = "List the users currently logged in on your system, along with the terminal they\n\
+    are logged in on, their login time, and the remote host they logged in from, if any.")]
+    Users,
+
     #[command(name = "device-name")]
     #[command(about = "Display your device's name")]
     #[command(long_about = "Show the configured name of your device.")]
@@ -81,171 +258,724 @@ enum Commands {
 
     #[command(name = "os")]
     #[command(about = "Display your system's OS name and version")]
-    #[command(long_about = "Show the name and version of the operating system installed on your system.")]
-    Os,
+    #[command(long_about = "Show the name and version of the operating system installed on your system.\n\
+    Use the --simple flag to only display the full distribution string, as a single named value.")]
+    Os {
+        #[arg(long)]
+        simple: bool,
+    },
 
     #[command(name = "architecture")]
     #[command(about = "Display your system's CPU architecture")]
-    #[command(long_about = "Show the architecture of the CPU installed on your system.")]
-    Architecture,
+    #[command(long_about = "Show the architecture of the CPU installed on your system, along with its\n\
+    pointer width and endianness.\n\
+    Example: x86_64 (64-bit, little-endian)\n\
+    Use the --simple flag to only display the architecture's name, as a single named value.\n\
+    Use the --micro-level flag to also display the CPU's microarchitecture level, which tells\n\
+    whether binaries built for it can run on the system: the x86-64 psABI level, from x86-64-v1\n\
+    to x86-64-v4, detected from CPUID, or the ARM architecture version, such as armv7 or armv8.\n\
+    It is reported as null on other architectures.")]
+    Architecture {
+        #[arg(long)]
+        simple: bool,
+
+        #[arg(long, conflicts_with = "simple")]
+        micro_level: bool,
+    },
+
+    #[command(name = "system-info", visible_alias = "fetch")]
+    #[command(about = "Display a summary of your system")]
+    #[command(long_about = "Show a curated summary of your system, as neofetch does: its hostname, OS, kernel,\n\
+    architecture, virtualization, uptime, CPU, RAM, and your login shell, along with an ASCII logo\n\
+    of the OS family.\n\
+    Use the --no-logo flag to omit the logo.")]
+    SystemInfo {
+        #[arg(long)]
+        no_logo: bool,
+    },
+
+    #[command(name = "uptime")]
+    #[command(about = "Display how long your system has been running")]
+    #[command(long_about = "Show how long your system has been running, along with the time it booted at, as the\n\
+    kernel reports it, so that it does not shift when the clock is adjusted.\n\
+    Example: up 3days 4h 12m, since 2023-04-05 16:08")]
+    Uptime,
+
+    #[command(name = "virt")]
+    #[command(about = "Display whether your system runs inside a container or a virtual machine")]
+    #[command(long_about = "Show whether your system runs inside a container or a virtual machine, along with the\n\
+    container runtime or hypervisor, when it can be told. Containers are detected from the files\n\
+    and environment container runtimes set up, and from the control groups of the init process;\n\
+    virtual machines from CPUID's hypervisor bit and vendor, and from the DMI table on Linux.\n\
+    Example: vm (kvm)")]
+    Virt,
+
+    #[command(name = "platform")]
+    #[command(about = "Display your system's platform")]
+    #[command(long_about = "Show the canonical OS family and CPU architecture of your system, as targeted by\n\
+    this binary. Unlike the os and architecture commands, its output is meant for automation.\n\
+    Example: linux/x86_64")]
+    Platform,
 
     #[command(name = "interfaces")]
     #[command(about = "Display your system's network interfaces")]
-    #[command(long_about = "List all the network interfaces configured on your system, presented in the order they are used.")]
-    Interfaces,
+    #[command(long_about = "List all the network interfaces configured on your system, presented in the order they are used.\n\
+    In JSON, interfaces are keyed by name, and hold their addresses split by family, along with\n\
+    their MAC address, MTU, and packet and error counters. Use the --flat flag to output one entry\n\
+    per address instead, and the --counters flag to also display the counters in text.\n\
+    The counters are cumulative since boot: a rising error count signals a faulty cable or NIC.")]
+    Interfaces {
+        #[arg(long)]
+        flat: bool,
+
+        #[arg(long)]
+        counters: bool,
+    },
+
+    #[command(name = "arp")]
+    #[command(about = "Display your system's ARP/neighbor cache")]
+    #[command(long_about = "List the entries of your system's ARP/neighbor cache, along with their MAC address,\n\
+    the network interface they are reachable through, and their state.")]
+    Arp,
+
+    #[command(name = "routes")]
+    #[command(about = "Display your system's routing table")]
+    #[command(long_about = "List the routes of your system's routing table, of both families, along with the\n\
+    gateway they forward packets to, the network interface they go through, and their metric.\n\
+    Routes reaching their destination directly on the link, without a gateway, are shown as on-link.\n\
+    Only the IPv4 routes are listed on Windows.\n\
+    Example: 0.0.0.0/0  via 192.168.1.1  dev eth0  metric 100")]
+    Routes,
 
     #[command(name = "disks")]
     #[command(about = "Display your system's disks")]
-    #[command(long_about = "Lists all the disks installed on your system, providing details such as disk name, type, free space, total capacity, and percentage of free space.")]
-    Disks,
+    #[command(long_about = "Lists all the disks installed on your system, providing details such as disk name, type, free space, total capacity, and percentage of free space.\n\
+    Disks mounted read-only are tagged [ro]; this is best-effort, and disks whose mount options\n\
+    cannot be read are reported as writable.\n\
+    Use the --smart flag to also report the health, temperature and power-on hours of the drive\n\
+    each disk belongs to, from its SMART data. It is read through smartctl, which must be installed\n\
+    and usually requires root privileges; when it cannot be read, such as for virtual disks, the\n\
+    health is reported as unknown rather than failing the command. Only supported on Linux.")]
+    Disks {
+        #[arg(long)]
+        smart: bool,
+    },
+
+    #[command(name = "disk-io")]
+    #[command(about = "Display your disks' read and write throughput")]
+    #[command(long_about = "Sample the read and write throughput of each disk installed on your system over\n\
+    an interval, and display it in bytes per second.\n\
+    Example: nvme0n1 ↓ 12.00 MiB/s ↑ 3.00 MiB/s\n\
+    Interrupting the sampling with Ctrl-C displays the throughput over the time elapsed so far.\n\
+    Use the --cumulative flag to display the total bytes read and written since boot instead,\n\
+    without waiting for an interval.")]
+    DiskIo {
+        #[arg(long, default_value = "1s", value_parser = parse_nonzero_duration)]
+        interval: Duration,
+
+        #[arg(long)]
+        cumulative: bool,
+    },
+
+    #[command(name = "doctor")]
+    #[command(about = "Check which capabilities work on your system")]
+    #[command(long_about = "Run a battery of self-checks reporting which capabilities work on your system, such as\n\
+    reading disks, listing network interfaces, reaching NTP servers, or resolving DNS queries.\n\
+    Exits with a nonzero status if any critical check fails.")]
+    Doctor,
+
+    #[command(name = "health")]
+    #[command(about = "Assess whether your system is healthy")]
+    #[command(long_about = "Assess the health of your system from a few signals: the RAM in use, the free space of\n\
+    the fullest disk, the five-minute load average per logical core, the clock's offset from NTP,\n\
+    and the connectivity, checked by looking up the public IP address. Display a single ok, warn or\n\
+    critical status, the reasons contributing to it, and the raw metrics.\n\
+    Read-only and pseudo filesystems, such as snaps' squashfs images, are left out of the disks.\n\
+    Example: warn: ram 92.1% used, disk 40.2% free, load 0.12/core, offset ±0.0123s, online\n\
+    Use the --threshold option to override the warning and critical thresholds of a signal, as\n\
+    signal=warning,critical, such as ram=80,95. The defaults are ram=70,90, disk=20,10, load=1,2\n\
+    and offset=60,120; the disk's thresholds are percentages of free space, and thus warn below them.\n\
+    A signal which cannot be measured, or a lack of connectivity, warns.\n\
+    Exits with status 0 when ok, 1 when warn, and 2 when critical.")]
+    Health {
+        #[arg(long = "threshold", value_name = "SIGNAL=WARN,CRIT")]
+        thresholds: Vec<health::ThresholdOverride>,
+    },
 
     #[command(name = "cpu")]
     #[command(about = "Display your system's CPU")]
-    #[command(long_about = "Show the name of the CPU installed on your system.")]
-    Cpu,
+    #[command(long_about = "Show the name of the CPU installed on your system.\n\
+    Use the --per-core flag to also display the frequency of each core, and the --usage flag\n\
+    to measure each core's usage as well. Use the --detailed flag to also display the CPU's cache\n\
+    sizes and the features it supports, such as avx2 or aes.")]
+    Cpu {
+        #[arg(long)]
+        per_core: bool,
+
+        #[arg(long)]
+        usage: bool,
+
+        #[arg(long)]
+        detailed: bool,
+    },
 
     #[command(name = "ram")]
     #[command(about = "Display your system's RAM")]
-    #[command(long_about = "Show the amount of RAM installed and used on your system.")]
-    Ram,
+    #[command(long_about = "Show the amount of RAM installed and used on your system.\n\
+    Use the --count option to sample the RAM in use several times, --interval apart, and display\n\
+    its trend instead; interrupting the sampling with Ctrl-C displays the trend so far.\n\
+    Example: used grew 120 MiB over 1m (312.00 MiB → 432.00 MiB)\n\
+    On Linux, use the --detailed flag to also display the RAM used by the page cache and the\n\
+    buffers, which is part of the available RAM, as the kernel reclaims it when processes need it.")]
+    Ram {
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        count: Option<u64>,
+
+        #[arg(long, conflicts_with = "count")]
+        detailed: bool,
+
+        #[arg(long, default_value = "1s", value_parser = humantime::parse_duration)]
+        interval: Duration,
+    },
+
+    #[command(name = "memory-pressure")]
+    #[command(about = "Display your system's memory pressure")]
+    #[command(long_about = "Show whether your system lacks memory, which its free RAM alone does not tell.\n\
+    On Linux, show the share of time tasks were stalled waiting for memory, from the kernel's\n\
+    pressure stall information; on macOS, show the memory pressure level the kernel reports.\n\
+    Example: memory pressure: some 2.1% / full 0.3% (10s avg)")]
+    MemoryPressure,
+
+    #[command(name = "render", hide = true)]
+    #[command(about = "Render a result captured as JSON")]
+    #[command(long_about = "Render the JSON output of a command, captured earlier, in another output format.\n\
+    The kind of result is read from the type field of output captured with --tagged. Otherwise, it is\n\
+    passed as the name of the command that produced it.\n\
+    Example: my render --from-json disks.json --format text")]
+    Render {
+        kind: Option<ResultKind>,
+
+        #[arg(long, value_name = "FILE")]
+        from_json: PathBuf,
+    },
 }
 
+impl Commands {
+    /// Returns whether the command samples over an interval, and thus
+    /// observes Ctrl-C to stop early rather than being killed by it.
+    fn is_long_running(&self) -> bool {
+        matches!(
+            self,
+            Commands::Time { drift: true, .. }
+                | Commands::DiskIo { cumulative: false, .. }
+                | Commands::Ram { count: Some(_), .. }
+        )
+    }
+}
+
+impl Cli {
+    /// Returns the nagios status of a command's result, along with the line
+    /// reporting it, unless the run is quiet, in which case the status is
+    /// only reported through the exit code.
+    fn nagios_report(&self, result: &Result<CommandResult>) -> (nagios::Status, Option<String>) {
+        let thresholds = nagios::Thresholds {
+            warning: self.warn,
+            critical: self.crit,
+        };
+
+        let report = match result {
+            Ok(result) => nagios::report(result.check(), &thresholds),
+            Err(err) => nagios::report_unknown(&format!("{:#}", err)),
+        };
+
+        (report.status, (!self.quiet).then_some(report.line))
+    }
+
+    /// Returns the output format requested through --format, --output, or
+    /// --json.
+    ///
+    /// The --json flag is global, so that it can follow the subcommand, and
+    /// clap cannot check its conflicts with the top-level format options:
+    /// it is rejected along with any format other than JSON.
+    fn requested_format(&self) -> Result<OutputFormat> {
+        let format = self.output.as_ref().map_or(self.format, |spec| spec.format);
+
+        match format {
+            OutputFormat::Auto | OutputFormat::Json if self.json => Ok(OutputFormat::Json),
+            _ if self.json => anyhow::bail!(
+                "the --json flag conflicts with the {} output format",
+                format.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default()
+            ),
+            _ => Ok(format),
+        }
+    }
+}
+
+/// Parses a duration, such as 1s or 500ms, which must not be zero, as for
+/// the interval a rate is computed over.
+fn parse_nonzero_duration(value: &str) -> Result<Duration> {
+    let duration = humantime::parse_duration(value)?;
+    if duration.is_zero() {
+        anyhow::bail!("the duration must be greater than zero");
+    }
+
+    Ok(duration)
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Enable human-readable panic messages
-    setup_panic!();
+    // Enable human-readable panic messages. The macro names the hook's info
+    // by the deprecated PanicInfo alias, which the allowance is scoped to.
+    #[allow(deprecated)]
+    {
+        setup_panic!();
+    }
 
     // Parse the CLI arguments
     let cli = Cli::parse();
 
+    if cli.raw_field.is_some() && (cli.json || cli.format != OutputFormat::Auto || cli.output.is_some()) {
+        anyhow::bail!("the --raw-field option cannot be used with --json, --format or --output");
+    }
+    if let (Some(warn), Some(crit)) = (cli.warn, cli.crit) {
+        if warn > crit {
+            anyhow::bail!("the --warn threshold ({}) cannot be greater than the --crit threshold ({})", warn, crit);
+        }
+    }
+
+    // Resolve the output format, and disable colors when the output
+    // format was automatically selected and stdout is not a terminal
+    let requested_format = cli.requested_format()?;
+    let format = requested_format.resolve();
+    if requested_format == OutputFormat::Auto && !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+
+    // Select the color theme of the text output
+    theme::set(cli.theme);
+
+    // Select how byte sizes are serialized
+    format::set_human_bytes(cli.human_json);
+
+    // The output spec's options take precedence over the defaults
+    let fields = cli.output.as_ref().and_then(|spec| spec.fields.as_deref());
+    let pretty = cli.output.as_ref().and_then(|spec| spec.pretty).unwrap_or(true);
+    if let Some(color) = cli.output.as_ref().and_then(|spec| spec.color) {
+        colored::control::set_override(color);
+    }
+
     // Execute the appropriate command
     if let Some(command) = &cli.command {
-        let result: CommandResult = match command {
-            Commands::Date => CommandResult::Date(
-                datetime::date().await
-                    .with_context(|| "looking up the system's date failed")?
-            ),
-            Commands::Time => CommandResult::Time(
-                datetime::time().await
-                    .with_context(|| "looking up the system's time failed")?
-            ),
-            Commands::Datetime => CommandResult::Datetime(
-                datetime::datetime().await
-                    .with_context(|| "looking up the system's datetime failed")?
-            ),
-            Commands::Dns => CommandResult::Dns(
-                network::list_dns_servers().await
-                    .with_context(|| "listing the system's dns servers failed")?
-            ),
-            Commands::Ips{ only } => match only {
-                Some(network::IpCategory::Public) => {
-                    let public_ip = network::query_public_ip(
-                        network::OPENDNS_SERVER_HOST,
-                        network::DNS_DEFAULT_PORT,
-                    )
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "looking up public ip failed; reason: querying dns server {} on port {} failed",
-                            network::OPENDNS_SERVER_HOST,
-                            network::DNS_DEFAULT_PORT
-                        )
-                    })?;
-                    CommandResult::Ips(vec![network::Ip {
-                        category: network::IpCategory::Public,
-                        address: public_ip,
-                    }])
-                },
-                Some(network::IpCategory::Local) => {
-                    let local_ip = local_ip_address::local_ip()
-                        .with_context(|| "looking up local ip failed; reason: querying local ip address failed")?;
-
-                    CommandResult::Ips(vec![network::Ip {
-                        category: network::IpCategory::Local,
-                        address: local_ip,
-                    }])
-                },
-                Some(network::IpCategory::Any) | None => {
-                    let public_ip = network::query_public_ip(
-                        network::OPENDNS_SERVER_HOST,
-                        network::DNS_DEFAULT_PORT,
-                    )
-                    .await
-                    .with_context(|| {
-                        format!(
-                            "listing ips failed; reason: querying dns server {} on port {} failed",
-                            network::OPENDNS_SERVER_HOST,
-                            network::DNS_DEFAULT_PORT
-                        )
-                    })?;
-
-                    let local_ip = local_ip_address::local_ip()
-                        .with_context(|| "listing ips failed; reason: querying local ip address failed")?;
-
-                    CommandResult::Ips(vec![
-                        network::Ip {
-                            category: network::IpCategory::Public,
-                            address: public_ip,
-                        },
-                        network::Ip {
-                            category: network::IpCategory::Local,
-                            address: local_ip,
-                        },
-                    ])
+        // Long-running commands share a token cancelled by Ctrl-C, while
+        // the others keep its default behavior of killing the process
+        let cancellation = if command.is_long_running() {
+            cancel::CancellationToken::on_ctrl_c()
+        } else {
+            cancel::CancellationToken::new()
+        };
+
+        let result = run(command, &cancellation).await;
+
+        // The nagios output format reports failures as an unknown status,
+        // and exits with the check's status code.
+        if format == OutputFormat::Nagios {
+            let (status, line) = cli.nagios_report(&result);
+            if let Some(line) = line {
+                println!("{}", line);
+            }
+
+            std::process::exit(status.exit_code());
+        }
+
+        // Failures are categorized, so that automation can branch on their
+        // exit code, or on their code in the JSON output format
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                if format == OutputFormat::Json && !cli.quiet {
+                    println!("{}", output::to_json(&error::ErrorReport::new(&err), pretty)?);
+                } else {
+                    eprintln!("Error: {:?}", err);
                 }
-            },
-            Commands::Hostname => CommandResult::Hostname(
-                system::hostname().await
-                    .with_context(|| "looking up the system's hostname failed")?
-            ),
-            Commands::Username => CommandResult::Username(
-                system::username().await
-                    .with_context(|| "looking up the user's username failed")?
-            ),
-            Commands::DeviceName => CommandResult::DeviceName(
-                system::device_name().await
-                    .with_context(|| "looking up the systems' device name failed")?
-            ),
-            Commands::Os => CommandResult::Os(
-                system::os().await
-                    .with_context(|| "looking up the system's OS name failed")?
-            ),
-            Commands::Architecture => CommandResult::Architecture(
-                system::architecture().await
-                    .with_context(|| "looking up the CPU's architecture fialed")?
-            ),
-            Commands::Interfaces => CommandResult::Interfaces(
-                network::interfaces().await
-                    .with_context(|| "listing the system's network interfaces failed")?
-            ),
-            Commands::Disks => CommandResult::Disks(
-                storage::list_disks().await
-                    .with_context(|| "listing the disks failed")?
-            ),
-            Commands::Cpu => CommandResult::Cpu(
-                system::cpus().await
-                    .with_context(|| "looking up the system's CPU information failed")?),
-            Commands::Ram => CommandResult::Ram(
-                system::ram().await
-                    .with_context(|| "looking up the system's RAM information failed")?
-            ),
+
+                std::process::exit(error::classify_error(&err).exit_code());
+            }
+        };
+        let captured_at = cli.timestamp.then(chrono::Local::now);
+
+        // The structured output formats render the serialized result, narrowed
+        // down to the requested non-empty fields, and stamped with its capture time
+        let to_value = || -> Result<serde_json::Value> {
+            let mut value = output::select_fields(serde_json::to_value(&result)?, fields);
+            if cli.compact_json {
+                value = output::without_empty(value);
+            }
+            if cli.tagged {
+                value = output::with_tag(value, &result.kind().tag());
+            }
+
+            Ok(match captured_at {
+                Some(captured_at) => output::with_timestamp(value, captured_at),
+                None => value,
+            })
         };
 
-        match cli.format {
+        match format {
+            // Quiet runs only report their outcome through the exit code
+            _ if cli.quiet => {}
+            // Raw fields are printed as a bare token, without even a trailing
+            // newline, so that they can be used as-is in shell arithmetic
+            _ if cli.raw_field.is_some() => {
+                let field = cli.raw_field.as_deref().unwrap_or_default();
+                print!("{}", output::raw_field(&serde_json::to_value(&result)?, field, cli.round)?);
+                std::io::stdout().flush()?;
+            }
             OutputFormat::Json => {
-                let json_repr = serde_json::to_string_pretty(&result)?;
+                let value = to_value()?;
+                let json_repr = if cli.schema_version {
+                    output::to_json(&output::Envelope::new(&value), pretty)?
+                } else {
+                    output::to_json(&value, pretty)?
+                };
                 println!("{}", json_repr);
             }
-            OutputFormat::Text => {
-                println!("{}", result);
+            OutputFormat::Text | OutputFormat::Auto => {
+                if let Some(captured_at) = captured_at {
+                    println!("# {}", captured_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, false));
+                }
+
+                // Only scalar results can do without the trailing newline, as
+                // it separates the items of the others
+                if cli.no_newline && result.is_scalar() {
+                    print!("{}", result);
+                    std::io::stdout().flush()?;
+                } else {
+                    if cli.no_newline {
+                        eprintln!("warning: --no-newline is ignored for results holding several items");
+                    }
+                    println!("{}", result);
+                }
             }
+            OutputFormat::Env => {
+                let value = to_value()?;
+                println!("{}", output::to_env(&value, result.env_prefix()).join("\n"));
+            }
+            OutputFormat::Markdown => {
+                let value = to_value()?;
+
+                // Tables are only fit to the terminal's width when written to
+                // one: piped output is never truncated
+                let max_width = if cli.no_truncate || !std::io::stdout().is_terminal() {
+                    None
+                } else {
+                    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+                };
+
+                let table = output::to_markdown(&value, max_width);
+                if !table.is_empty() {
+                    println!("{}", table);
+                }
+            }
+            OutputFormat::Nagios => unreachable!("the nagios output format is handled above"),
+        }
+
+        // A result cut short by Ctrl-C is still displayed, but the exit
+        // status tells it apart from a complete one
+        let exit_code = if cancellation.is_cancelled() {
+            error::ErrorCode::Interrupted.exit_code()
+        } else {
+            result.exit_code()
+        };
+        if exit_code != 0 {
+            std::process::exit(exit_code);
         }
     }
 
     Ok(())
 }
 
+/// Executes a command, and returns its result.
+async fn run(command: &Commands, cancellation: &cancel::CancellationToken) -> Result<CommandResult> {
+    let result = match command {
+        Commands::Date => CommandResult::Date(
+            datetime::date().await
+                .with_context(|| "looking up the system's date failed")?
+        ),
+        Commands::Time { server, drift: true, interval, .. } => CommandResult::Drift(
+            datetime::drift(server.as_deref(), *interval, cancellation).await
+                .with_context(|| "measuring the system clock's drift failed")?
+        ),
+        Commands::Time { no_offset, verbose, ntp_details, server, .. } => CommandResult::Time(
+            datetime::time(server.as_deref()).await
+                .with_context(|| "looking up the system's time failed")?
+                .no_offset(*no_offset)
+                .verbose(*verbose)
+                .ntp_details(*ntp_details)
+        ),
+        Commands::Datetime { oneline, no_offset, server } => CommandResult::Datetime(
+            datetime::datetime(server.as_deref()).await
+                .with_context(|| "looking up the system's datetime failed")?
+                .oneline(*oneline)
+                .no_offset(*no_offset)
+        ),
+        Commands::Dns { count_only: true, .. } => CommandResult::DnsServerCount(network::DnsServerCount {
+            dns_server_count: network::list_dns_servers().await
+                .with_context(|| "listing the system's dns servers failed")?
+                .len(),
+        }),
+        Commands::Dns { indexed, .. } => CommandResult::Dns(network::DnsServers {
+            servers: network::list_dns_servers().await
+                .with_context(|| "listing the system's dns servers failed")?,
+            indexed: *indexed,
+        }),
+        Commands::Ips { source: Some(destination), .. } => CommandResult::SourceIp(
+            network::source_ip(*destination)
+                .with_context(|| "looking up the source ip failed")?
+        ),
+        Commands::Ips { only, method, consensus: true, .. } => {
+            if *only != Some(network::IpCategory::Public) {
+                anyhow::bail!("the --consensus flag requires --only public");
+            }
+            if *method != network::PublicIpMethod::Dns {
+                anyhow::bail!("querying several resolvers is only supported by the dns method");
+            }
+
+            CommandResult::PublicIpConsensus(
+                network::query_public_ip_consensus().await
+                    .with_context(|| "looking up public ip failed")?
+            )
+        }
+        Commands::Ips{ only, method, all, best_effort, bind_interface, .. } => match only {
+            Some(network::IpCategory::Public) => {
+                let public_ip = lookup_public_ip(*method, bind_interface.as_deref())
+                    .await
+                    .with_context(|| "looking up public ip failed")?;
+
+                CommandResult::Ips(vec![network::Ip {
+                    category: network::IpCategory::Public,
+                    address: public_ip,
+                    via: Some(method.via().to_string()),
+                }])
+            },
+            Some(network::IpCategory::Local) => {
+                let local_ips = lookup_local_ips(*all)
+                    .await
+                    .with_context(|| "looking up local ip failed")?;
+
+                CommandResult::Ips(
+                    local_ips
+                        .into_iter()
+                        .map(|address| network::Ip {
+                            category: network::IpCategory::Local,
+                            address,
+                            via: None,
+                        })
+                        .collect(),
+                )
+            },
+            Some(network::IpCategory::Any) | None => {
+                // The public lookup goes over the network, so the local one
+                // runs alongside it rather than waiting for it
+                let (public_ip, local_ips) = tokio::join!(
+                    lookup_public_ip(*method, bind_interface.as_deref()),
+                    lookup_local_ips(*all),
+                );
+
+                let local_ips = local_ips.with_context(|| "listing ips failed")?;
+
+                let mut ips = Vec::new();
+                match public_ip {
+                    Ok(public_ip) => ips.push(network::Ip {
+                        category: network::IpCategory::Public,
+                        address: public_ip,
+                        via: Some(method.via().to_string()),
+                    }),
+                    Err(err) if *best_effort => {
+                        eprintln!("warning: looking up public ip failed: {:#}", err);
+                    }
+                    Err(err) => return Err(err.context("listing ips failed")),
+                }
+                ips.extend(local_ips.into_iter().map(|address| network::Ip {
+                    category: network::IpCategory::Local,
+                    address,
+                    via: None,
+                }));
+
+                CommandResult::Ips(ips)
+            }
+        },
+        Commands::Hostname { aliases: true, .. } => CommandResult::HostnameAliases(
+            system::hostname_aliases().await
+                .with_context(|| "looking up the system's hostname aliases failed")?
+        ),
+        Commands::Hostname { fqdn: false, .. } => CommandResult::Hostname(
+            system::hostname().await
+                .with_context(|| "looking up the system's hostname failed")?
+        ),
+        Commands::Hostname { fqdn: true, .. } => CommandResult::Hostname(
+            system::fqdn().await
+                .with_context(|| "looking up the system's fully qualified domain name failed")?
+        ),
+        Commands::Username => CommandResult::Username(
+            system::username().await
+                .with_context(|| "looking up the user's username failed")?
+        ),
+        Commands::Users => CommandResult::Users(
+            system::users().await
+                .with_context(|| "listing the logged-in users failed")?
+        ),
+        Commands::DeviceName => CommandResult::DeviceName(
+            system::device_name().await
+                .with_context(|| "looking up the systems' device name failed")?
+        ),
+        Commands::Os { simple: false } => CommandResult::Os(
+            system::os().await
+                .with_context(|| "looking up the system's OS name failed")?
+        ),
+        Commands::Os { simple: true } => CommandResult::OsName(
+            system::os_name().await
+                .with_context(|| "looking up the system's OS name failed")?
+        ),
+        Commands::Architecture { simple: false, micro_level } => CommandResult::Architecture(
+            system::architecture(*micro_level).await
+                .with_context(|| "looking up the CPU's architecture failed")?
+        ),
+        Commands::Architecture { simple: true, .. } => CommandResult::ArchitectureName(
+            system::architecture_name().await
+                .with_context(|| "looking up the CPU's architecture failed")?
+        ),
+        Commands::Interfaces { flat, counters } => CommandResult::Interfaces(network::Interfaces {
+            interfaces: network::interfaces(true).await
+                .with_context(|| "listing the system's network interfaces failed")?,
+            flat: *flat,
+            counters: *counters,
+        }),
+        Commands::Routes => CommandResult::Routes(network::Routes(
+            network::routes().await
+                .with_context(|| "listing the system's routes failed")?
+        )),
+        Commands::Arp => CommandResult::Arp(
+            network::neighbors().await
+                .with_context(|| "listing the system's arp cache failed")?
+        ),
+        Commands::Disks { smart } => CommandResult::Disks(storage::Disks(
+            storage::list_disks(*smart).await
+                .with_context(|| "listing the disks failed")?
+        )),
+        Commands::DiskIo { interval, cumulative: false } => CommandResult::DiskIo(
+            storage::disks_io(*interval, cancellation).await
+                .with_context(|| "sampling the disks' io throughput failed")?
+        ),
+        Commands::DiskIo { cumulative: true, .. } => CommandResult::DiskIoTotals(
+            storage::disks_io_totals().await
+                .with_context(|| "reading the disks' io totals failed")?
+        ),
+        Commands::Doctor => CommandResult::Doctor(doctor::diagnose().await),
+        Commands::Health { thresholds } => CommandResult::Health(
+            health::assess(&health::Thresholds::with_overrides(thresholds)).await
+        ),
+        Commands::Cpu { per_core, usage, detailed } => CommandResult::Cpu(
+            system::cpus(*usage, *detailed).await
+                .with_context(|| "looking up the system's CPU information failed")?
+                .per_core(*per_core)
+        ),
+        Commands::Ram { count: None, detailed, .. } => CommandResult::Ram(
+            system::ram(*detailed).await
+                .with_context(|| "looking up the system's RAM information failed")?
+        ),
+        Commands::Ram { count: Some(count), interval, .. } => CommandResult::RamTrend(
+            system::ram_trend(*count, *interval, cancellation).await
+                .with_context(|| "sampling the system's RAM usage failed")?
+        ),
+        Commands::MemoryPressure => CommandResult::MemoryPressure(
+            system::memory_pressure().await
+                .with_context(|| "looking up the system's memory pressure failed")?
+        ),
+        Commands::SystemInfo { no_logo } => CommandResult::SystemInfo(
+            fetch::system_info(!*no_logo).await
+                .with_context(|| "looking up the system's information failed")?
+        ),
+        Commands::Uptime => CommandResult::Uptime(
+            system::uptime().await
+                .with_context(|| "looking up the system's uptime failed")?
+        ),
+        Commands::Virt => CommandResult::Virt(
+            system::virtualization().await
+                .with_context(|| "detecting the system's virtualization failed")?
+        ),
+        Commands::Platform => CommandResult::Platform(
+            system::platform().await
+                .with_context(|| "looking up the system's platform failed")?
+        ),
+        Commands::Render { kind, from_json } => {
+            let json = std::fs::read_to_string(from_json)
+                .with_context(|| format!("reading {} failed", from_json.display()))?;
+
+            CommandResult::from_json(*kind, &json)
+                .with_context(|| format!("rendering {} failed", from_json.display()))?
+        }
+    };
+
+    Ok(result)
+}
+
+/// Looks up the local IP addresses: the primary one, or those of all the
+/// network interfaces.
+async fn lookup_local_ips(all: bool) -> Result<Vec<std::net::IpAddr>> {
+    if all {
+        return network::local_ips()
+            .await
+            .with_context(|| "reason: listing the network interfaces failed");
+    }
+
+    let local_ip = local_ip_address::local_ip()
+        .with_context(|| "reason: querying local ip address failed")?;
+
+    Ok(vec![local_ip])
+}
+
+/// Looks up the public IP address using the provided method, optionally
+/// through the provided network interface.
+async fn lookup_public_ip(method: network::PublicIpMethod, interface: Option<&str>) -> Result<std::net::IpAddr> {
+    match (method, interface) {
+        (network::PublicIpMethod::Dns, Some(interface)) => network::query_public_ip_via(
+            network::OPENDNS_SERVER_HOST,
+            network::DNS_DEFAULT_PORT,
+            interface,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "querying dns server {} on port {} through interface {} failed",
+                network::OPENDNS_SERVER_HOST,
+                network::DNS_DEFAULT_PORT,
+                interface
+            )
+        }),
+        (network::PublicIpMethod::Https, Some(_)) => {
+            anyhow::bail!("binding to a network interface is only supported by the dns method")
+        }
+        (network::PublicIpMethod::Dns, None) => network::query_public_ip(
+            network::OPENDNS_SERVER_HOST,
+            network::DNS_DEFAULT_PORT,
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "querying dns server {} on port {} failed",
+                network::OPENDNS_SERVER_HOST,
+                network::DNS_DEFAULT_PORT
+            )
+        }),
+        (network::PublicIpMethod::Https, None) => network::query_public_ip_https(
+            network::IPIFY_URL,
+            network::PUBLIC_IP_TIMEOUT,
+        )
+        .await
+        .with_context(|| format!("querying https endpoint {} failed", network::IPIFY_URL)),
+    }
+}
+
 /// CommandResult holds the result of a command.
 ///
 /// This is used to facilitate factorizing the command execution,
@@ -253,19 +983,239 @@ async fn main() -> Result<()> {
 /// in a single place.
 enum CommandResult {
     Ips(Vec<network::Ip>),
-    Dns(Vec<String>),
+    PublicIpConsensus(network::PublicIpConsensus),
+    SourceIp(network::SourceIp),
+    Dns(network::DnsServers),
+    DnsServerCount(network::DnsServerCount),
     Date(datetime::Date),
     Time(datetime::Time),
+    Drift(datetime::Drift),
     Datetime(datetime::Datetime),
     Hostname(output::Named),
+    HostnameAliases(system::HostnameAliases),
     Username(output::Named),
+    Users(Vec<system::LoggedInUser>),
     DeviceName(output::Named),
-    Os(output::Named),
-    Architecture(output::Named),
-    Interfaces(Vec<network::Interface>),
-    Disks(Vec<storage::DiskInfo>),
+    Os(system::Os),
+    OsName(output::Named),
+    Architecture(system::Architecture),
+    ArchitectureName(output::Named),
+    Interfaces(network::Interfaces),
+    Arp(Vec<network::Neighbor>),
+    Routes(network::Routes),
+    Disks(storage::Disks),
+    DiskIo(Vec<storage::DiskIo>),
+    DiskIoTotals(Vec<storage::DiskIoTotals>),
+    Doctor(doctor::Diagnostics),
+    Health(health::Health),
     Cpu(system::Cpu),
-    Ram(system::Ram)
+    Ram(system::Ram),
+    RamTrend(system::RamTrend),
+    MemoryPressure(system::MemoryPressure),
+    Platform(system::Platform),
+    SystemInfo(fetch::SystemInfo),
+    Uptime(system::Uptime),
+    Virt(system::Virtualization),
+}
+
+impl CommandResult {
+    /// Rebuilds the result of a command from its captured JSON output.
+    ///
+    /// The kind of result selects the variant to deserialize into, since
+    /// several results share the same shape. It is read from the type field
+    /// of a tagged result, unless provided. Commands whose flags change the
+    /// shape of their result are told apart by the fields it holds.
+    fn from_json(kind: Option<ResultKind>, json: &str) -> Result<CommandResult> {
+        let value = output::unwrap_captured(serde_json::from_str(json)?);
+        let tagged_kind = value.get("type").and_then(Value::as_str).and_then(ResultKind::from_tag);
+        let kind = match (kind, tagged_kind) {
+            (Some(kind), Some(tagged_kind)) if kind != tagged_kind => {
+                return Err(error::categorized(
+                    error::ErrorCode::ParseError,
+                    format!("the result is tagged as {}, not {}", tagged_kind.tag(), kind.tag()),
+                ))
+            }
+            (Some(kind), _) | (None, Some(kind)) => kind,
+            (None, None) => {
+                return Err(error::categorized(
+                    error::ErrorCode::ParseError,
+                    "the result is not tagged with its kind, which must then be provided",
+                ))
+            }
+        };
+
+        let value = output::without_tag(value, &kind.tag());
+        let has_field = |field: &str| match &value {
+            Value::Object(map) => map.contains_key(field),
+            Value::Array(items) => items.first().and_then(Value::as_object).is_some_and(|map| map.contains_key(field)),
+            _ => false,
+        };
+
+        let result = match kind {
+            ResultKind::Ips if has_field("consensus") => CommandResult::PublicIpConsensus(serde_json::from_value(value)?),
+            ResultKind::Ips if has_field("source_ip") => CommandResult::SourceIp(serde_json::from_value(value)?),
+            ResultKind::Ips => CommandResult::Ips(serde_json::from_value(value)?),
+            ResultKind::Dns if has_field("dns_server_count") => CommandResult::DnsServerCount(serde_json::from_value(value)?),
+            ResultKind::Dns => CommandResult::Dns(serde_json::from_value(value)?),
+            ResultKind::Date => CommandResult::Date(serde_json::from_value(value)?),
+            ResultKind::Time if has_field("ppm") => CommandResult::Drift(serde_json::from_value(value)?),
+            ResultKind::Time => CommandResult::Time(serde_json::from_value(value)?),
+            ResultKind::Datetime => CommandResult::Datetime(serde_json::from_value(value)?),
+            ResultKind::Hostname if has_field("aliases") => CommandResult::HostnameAliases(serde_json::from_value(value)?),
+            ResultKind::Hostname => CommandResult::Hostname(serde_json::from_value(value)?),
+            ResultKind::Username => CommandResult::Username(serde_json::from_value(value)?),
+            ResultKind::Users => CommandResult::Users(serde_json::from_value(value)?),
+            ResultKind::DeviceName => CommandResult::DeviceName(serde_json::from_value(value)?),
+            ResultKind::Os if has_field("distro") => CommandResult::Os(serde_json::from_value(value)?),
+            ResultKind::Os => CommandResult::OsName(serde_json::from_value(value)?),
+            ResultKind::Architecture if has_field("arch") => CommandResult::Architecture(serde_json::from_value(value)?),
+            ResultKind::Architecture => CommandResult::ArchitectureName(serde_json::from_value(value)?),
+            ResultKind::Platform => CommandResult::Platform(serde_json::from_value(value)?),
+            ResultKind::SystemInfo => CommandResult::SystemInfo(serde_json::from_value(value)?),
+            ResultKind::Uptime => CommandResult::Uptime(serde_json::from_value(value)?),
+            ResultKind::Virt => CommandResult::Virt(serde_json::from_value(value)?),
+            ResultKind::Interfaces => CommandResult::Interfaces(serde_json::from_value(value)?),
+            ResultKind::Arp => CommandResult::Arp(serde_json::from_value(value)?),
+            ResultKind::Routes => CommandResult::Routes(serde_json::from_value(value)?),
+            ResultKind::Disks => CommandResult::Disks(serde_json::from_value(value)?),
+            ResultKind::DiskIo if has_field("read_bytes_total") => CommandResult::DiskIoTotals(serde_json::from_value(value)?),
+            ResultKind::DiskIo => CommandResult::DiskIo(serde_json::from_value(value)?),
+            ResultKind::Doctor => CommandResult::Doctor(serde_json::from_value(value)?),
+            ResultKind::Health => CommandResult::Health(serde_json::from_value(value)?),
+            ResultKind::Cpu => CommandResult::Cpu(serde_json::from_value(value)?),
+            ResultKind::Ram if has_field("samples") => CommandResult::RamTrend(serde_json::from_value(value)?),
+            ResultKind::Ram => CommandResult::Ram(serde_json::from_value(value)?),
+            ResultKind::MemoryPressure => CommandResult::MemoryPressure(serde_json::from_value(value)?),
+        };
+
+        Ok(result)
+    }
+
+    /// Returns the kind of the result, named after the command producing it.
+    fn kind(&self) -> ResultKind {
+        match self {
+            CommandResult::Ips(_) | CommandResult::PublicIpConsensus(_) | CommandResult::SourceIp(_) => ResultKind::Ips,
+            CommandResult::Dns(_) | CommandResult::DnsServerCount(_) => ResultKind::Dns,
+            CommandResult::Date(_) => ResultKind::Date,
+            CommandResult::Time(_) | CommandResult::Drift(_) => ResultKind::Time,
+            CommandResult::Datetime(_) => ResultKind::Datetime,
+            CommandResult::Hostname(_) | CommandResult::HostnameAliases(_) => ResultKind::Hostname,
+            CommandResult::Username(_) => ResultKind::Username,
+            CommandResult::Users(_) => ResultKind::Users,
+            CommandResult::DeviceName(_) => ResultKind::DeviceName,
+            CommandResult::Os(_) | CommandResult::OsName(_) => ResultKind::Os,
+            CommandResult::Architecture(_) | CommandResult::ArchitectureName(_) => ResultKind::Architecture,
+            CommandResult::Platform(_) => ResultKind::Platform,
+            CommandResult::SystemInfo(_) => ResultKind::SystemInfo,
+            CommandResult::Uptime(_) => ResultKind::Uptime,
+            CommandResult::Virt(_) => ResultKind::Virt,
+            CommandResult::Interfaces(_) => ResultKind::Interfaces,
+            CommandResult::Arp(_) => ResultKind::Arp,
+            CommandResult::Routes(_) => ResultKind::Routes,
+            CommandResult::Disks(_) => ResultKind::Disks,
+            CommandResult::DiskIo(_) | CommandResult::DiskIoTotals(_) => ResultKind::DiskIo,
+            CommandResult::Doctor(_) => ResultKind::Doctor,
+            CommandResult::Health(_) => ResultKind::Health,
+            CommandResult::Cpu(_) => ResultKind::Cpu,
+            CommandResult::Ram(_) | CommandResult::RamTrend(_) => ResultKind::Ram,
+            CommandResult::MemoryPressure(_) => ResultKind::MemoryPressure,
+        }
+    }
+
+    /// Returns the prefix of the variables holding the result in the env output format.
+    ///
+    /// Named results already serialize under their own name, and thus
+    /// only use the common prefix.
+    fn env_prefix(&self) -> &'static str {
+        match self {
+            CommandResult::Ips(_) | CommandResult::PublicIpConsensus(_) | CommandResult::SourceIp(_) => "MY_IP",
+            CommandResult::Dns(_) => "MY_DNS",
+            CommandResult::DnsServerCount(_) => "MY",
+            CommandResult::Date(_) => "MY_DATE",
+            CommandResult::Time(_) | CommandResult::Drift(_) => "MY_TIME",
+            CommandResult::Datetime(_) => "MY_DATETIME",
+            CommandResult::Hostname(_)
+            | CommandResult::HostnameAliases(_)
+            | CommandResult::Username(_)
+            | CommandResult::DeviceName(_)
+            | CommandResult::OsName(_)
+            | CommandResult::ArchitectureName(_) => "MY",
+            CommandResult::Architecture(_) => "MY_ARCHITECTURE",
+            CommandResult::Os(_) => "MY_OS",
+            CommandResult::Users(_) => "MY_USER",
+            CommandResult::Interfaces(_) => "MY_INTERFACE",
+            CommandResult::Arp(_) => "MY_ARP",
+            CommandResult::Routes(_) => "MY_ROUTE",
+            CommandResult::Disks(_) => "MY_DISK",
+            CommandResult::DiskIo(_) | CommandResult::DiskIoTotals(_) => "MY_DISK_IO",
+            CommandResult::Doctor(_) => "MY_DOCTOR",
+            CommandResult::Health(_) => "MY_HEALTH",
+            CommandResult::Cpu(_) => "MY_CPU",
+            CommandResult::Ram(_) | CommandResult::RamTrend(_) => "MY_RAM",
+            CommandResult::MemoryPressure(_) => "MY_MEMORY_PRESSURE",
+            CommandResult::Platform(_) => "MY_PLATFORM",
+            CommandResult::SystemInfo(_) => "MY_SYSTEM",
+            CommandResult::Uptime(_) => "MY_UPTIME",
+            CommandResult::Virt(_) => "MY_VIRT",
+        }
+    }
+}
+
+impl CommandResult {
+    /// Returns the exit code the result calls for, once output.
+    fn exit_code(&self) -> i32 {
+        match self {
+            CommandResult::Doctor(diagnostics) if diagnostics.critical_failure() => 1,
+            CommandResult::DnsServerCount(count) if count.dns_server_count == 0 => 1,
+            CommandResult::Health(health) => health.status.exit_code(),
+            _ => 0,
+        }
+    }
+
+    /// Returns whether the result is a single value, whose text output is
+    /// a single line.
+    fn is_scalar(&self) -> bool {
+        matches!(
+            self,
+            CommandResult::Hostname(_)
+                | CommandResult::Username(_)
+                | CommandResult::DeviceName(_)
+                | CommandResult::OsName(_)
+                | CommandResult::ArchitectureName(_)
+                | CommandResult::SourceIp(_)
+                | CommandResult::DnsServerCount(_)
+        )
+    }
+
+    /// Returns the metrics the nagios output format checks, along with
+    /// their default thresholds, or None if the result has nothing to check.
+    fn check(&self) -> Option<nagios::Check> {
+        match self {
+            CommandResult::Ram(ram) => Some(nagios::Check {
+                metrics: vec![nagios::Metric::percentage("ram_used", ram.used_percent())],
+                warning: 70.0,
+                critical: 90.0,
+            }),
+            CommandResult::Disks(disks) => Some(nagios::Check {
+                metrics: disks
+                    .0
+                    .iter()
+                    // Read-only and pseudo filesystems, such as snaps, are always full
+                    .filter(|disk| disk.is_monitored())
+                    // A device can be mounted several times, while mount points are unique
+                    .map(|disk| nagios::Metric::percentage(&disk.mount_point, disk.used_percent()))
+                    .collect(),
+                warning: 80.0,
+                critical: 90.0,
+            }),
+            CommandResult::Time(time) => Some(nagios::Check {
+                metrics: vec![nagios::Metric::seconds("offset", time.offset().abs())],
+                warning: 60.0,
+                critical: 120.0,
+            }),
+            _ => None,
+        }
+    }
 }
 
 impl Display for CommandResult {
@@ -275,41 +1225,79 @@ impl Display for CommandResult {
                 let ips = ips.iter().map(ToString::to_string).collect::<Vec<String>>();
                 write!(f, "{}", ips.join("\n"))
             }
-            CommandResult::Dns(dns) => {
-                write!(f, "{}", dns.join("\n"))
-            }
+            CommandResult::PublicIpConsensus(consensus) => consensus.fmt(f),
+            CommandResult::SourceIp(source) => source.fmt(f),
+            CommandResult::Dns(dns) => dns.fmt(f),
+            CommandResult::DnsServerCount(count) => count.fmt(f),
             CommandResult::Date(date) => date.fmt(f),
             CommandResult::Time(time) => time.fmt(f),
+            CommandResult::Drift(drift) => drift.fmt(f),
             CommandResult::Datetime(datetime) => datetime.fmt(f),
             CommandResult::Hostname(hostname) => hostname.fmt(f),
+            CommandResult::HostnameAliases(aliases) => aliases.fmt(f),
             CommandResult::Username(username) => username.fmt(f),
+            CommandResult::Users(users) => {
+                write!(
+                    f,
+                    "{}",
+                    users
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                )
+            },
             CommandResult::DeviceName(device_name) => device_name.fmt(f),
             CommandResult::Os(os) => os.fmt(f),
+            CommandResult::OsName(os) => os.fmt(f),
             CommandResult::Architecture(architecture) => architecture.fmt(f),
-            CommandResult::Interfaces(interfaces) => {
+            CommandResult::ArchitectureName(architecture) => architecture.fmt(f),
+            CommandResult::Interfaces(interfaces) => interfaces.fmt(f),
+            CommandResult::Arp(neighbors) => {
+                write!(
+                    f,
+                    "{}",
+                    neighbors
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                )
+            },
+            CommandResult::Routes(routes) => routes.fmt(f),
+            CommandResult::Disks(disks) => disks.fmt(f),
+            CommandResult::DiskIo(disks_io) => {
                 write!(
                     f,
                     "{}",
-                    interfaces
+                    disks_io
                         .iter()
                         .map(ToString::to_string)
                         .collect::<Vec<String>>()
                         .join("\n")
                 )
             },
-            CommandResult::Disks(disks) => {
+            CommandResult::DiskIoTotals(totals) => {
                 write!(
                     f,
                     "{}",
-                    disks
+                    totals
                         .iter()
                         .map(ToString::to_string)
                         .collect::<Vec<String>>()
                         .join("\n")
                 )
             },
+            CommandResult::Doctor(diagnostics) => diagnostics.fmt(f),
+            CommandResult::Health(health) => health.fmt(f),
             CommandResult::Cpu(cpu) => cpu.fmt(f),
             CommandResult::Ram(ram) => ram.fmt(f),
+            CommandResult::RamTrend(trend) => trend.fmt(f),
+            CommandResult::MemoryPressure(pressure) => pressure.fmt(f),
+            CommandResult::Platform(platform) => platform.fmt(f),
+            CommandResult::SystemInfo(info) => info.fmt(f),
+            CommandResult::Uptime(uptime) => uptime.fmt(f),
+            CommandResult::Virt(virtualization) => virtualization.fmt(f),
         }
     }
 }
@@ -321,25 +1309,180 @@ impl Serialize for CommandResult {
     {
         match self {
             CommandResult::Ips(ips) => ips.serialize(serializer),
+            CommandResult::PublicIpConsensus(consensus) => consensus.serialize(serializer),
+            CommandResult::SourceIp(source) => source.serialize(serializer),
             CommandResult::Dns(dns) => dns.serialize(serializer),
+            CommandResult::DnsServerCount(count) => count.serialize(serializer),
             CommandResult::Date(date) => date.serialize(serializer),
             CommandResult::Time(time) => time.serialize(serializer),
+            CommandResult::Drift(drift) => drift.serialize(serializer),
             CommandResult::Datetime(datetime) => datetime.serialize(serializer),
             CommandResult::Hostname(hostname) => hostname.serialize(serializer),
+            CommandResult::HostnameAliases(aliases) => aliases.serialize(serializer),
             CommandResult::Username(username) => username.serialize(serializer),
+            CommandResult::Users(users) => users.serialize(serializer),
             CommandResult::DeviceName(device_name) => device_name.serialize(serializer),
             CommandResult::Os(os) => os.serialize(serializer),
+            CommandResult::OsName(os) => os.serialize(serializer),
             CommandResult::Architecture(architecture) => architecture.serialize(serializer),
+            CommandResult::ArchitectureName(architecture) => architecture.serialize(serializer),
             CommandResult::Interfaces(interfaces) => interfaces.serialize(serializer),
+            CommandResult::Arp(neighbors) => neighbors.serialize(serializer),
+            CommandResult::Routes(routes) => routes.serialize(serializer),
             CommandResult::Disks(disks) => disks.serialize(serializer),
+            CommandResult::DiskIo(disks_io) => disks_io.serialize(serializer),
+            CommandResult::DiskIoTotals(totals) => totals.serialize(serializer),
+            CommandResult::Doctor(diagnostics) => diagnostics.serialize(serializer),
+            CommandResult::Health(health) => health.serialize(serializer),
             CommandResult::Cpu(cpu) => cpu.serialize(serializer),
             CommandResult::Ram(ram) => ram.serialize(serializer),
+            CommandResult::RamTrend(trend) => trend.serialize(serializer),
+            CommandResult::MemoryPressure(pressure) => pressure.serialize(serializer),
+            CommandResult::Platform(platform) => platform.serialize(serializer),
+            CommandResult::SystemInfo(info) => info.serialize(serializer),
+            CommandResult::Uptime(uptime) => uptime.serialize(serializer),
+            CommandResult::Virt(virtualization) => virtualization.serialize(serializer),
         }
     }
 }
 
+/// The kind of a command's result, named after the command producing it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum ResultKind {
+    Ips,
+    Dns,
+    Date,
+    Time,
+    Datetime,
+    Hostname,
+    Username,
+    Users,
+    DeviceName,
+    Os,
+    Architecture,
+    Platform,
+    SystemInfo,
+    Uptime,
+    Virt,
+    Interfaces,
+    Arp,
+    Routes,
+    Disks,
+    DiskIo,
+    Doctor,
+    Health,
+    Cpu,
+    Ram,
+    MemoryPressure,
+}
+
+impl ResultKind {
+    /// Returns the tag of the kind of result: its name, in snake_case as
+    /// all the serialized values are.
+    fn tag(self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().replace('-', "_"))
+            .unwrap_or_default()
+    }
+
+    /// Returns the kind of result a tag names, if any.
+    fn from_tag(tag: &str) -> Option<ResultKind> {
+        ResultKind::value_variants().iter().copied().find(|kind| kind.tag() == tag)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum OutputFormat {
+    Auto,
     Json,
     Text,
-}
\ No newline at end of file
+    Env,
+    Nagios,
+    Markdown,
+}
+
+impl OutputFormat {
+    /// Resolves the automatic output format based on the output destination.
+    ///
+    /// The automatic format outputs text, unless the `MY_AUTO_JSON=1` hint
+    /// is set in the environment, in which case it outputs JSON.
+    /// Explicitly selected formats are returned as-is.
+    fn resolve(self) -> OutputFormat {
+        match self {
+            OutputFormat::Auto if std::env::var("MY_AUTO_JSON").as_deref() == Ok("1") => OutputFormat::Json,
+            OutputFormat::Auto => OutputFormat::Text,
+            format => format,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requested_format(args: &[&str]) -> Result<OutputFormat> {
+        Cli::try_parse_from(std::iter::once("my").chain(args.iter().copied()))?.requested_format()
+    }
+
+    #[test]
+    fn disk_io_rejects_a_zero_interval() {
+        assert!(Cli::try_parse_from(["my", "disk-io", "--interval", "0s"]).is_err());
+        assert!(Cli::try_parse_from(["my", "disk-io", "--interval", "500ms"]).is_ok());
+    }
+
+    #[test]
+    fn json_flag_follows_the_subcommand() {
+        assert_eq!(requested_format(&["os", "--json"]).unwrap(), OutputFormat::Json);
+        assert_eq!(requested_format(&["--json", "os"]).unwrap(), OutputFormat::Json);
+    }
+
+    #[test]
+    fn json_flag_accepts_a_matching_format() {
+        assert_eq!(requested_format(&["-f", "json", "os", "--json"]).unwrap(), OutputFormat::Json);
+        assert_eq!(requested_format(&["--output", "json;pretty=false", "os", "--json"]).unwrap(), OutputFormat::Json);
+    }
+
+    #[test]
+    fn json_flag_rejects_a_conflicting_format() {
+        assert!(requested_format(&["-f", "text", "os", "--json"]).is_err());
+        assert!(requested_format(&["--output", "markdown", "os", "--json"]).is_err());
+    }
+
+    #[test]
+    fn render_reads_the_kind_from_the_tag() {
+        let tagged = r#"{"type": "hostname", "hostname": "box"}"#;
+        let wrapped = r#"{"type": "users", "data": []}"#;
+
+        assert!(matches!(CommandResult::from_json(None, tagged).unwrap(), CommandResult::Hostname(_)));
+        assert!(matches!(CommandResult::from_json(None, wrapped).unwrap(), CommandResult::Users(_)));
+        assert!(matches!(CommandResult::from_json(Some(ResultKind::Hostname), tagged).unwrap(), CommandResult::Hostname(_)));
+    }
+
+    #[test]
+    fn render_rejects_a_missing_or_mismatched_kind() {
+        assert!(CommandResult::from_json(None, r#"{"hostname": "box"}"#).is_err());
+        assert!(CommandResult::from_json(Some(ResultKind::Os), r#"{"type": "hostname", "hostname": "box"}"#).is_err());
+    }
+
+    #[test]
+    fn format_names_are_case_insensitive() {
+        assert_eq!(requested_format(&["-f", "JSON", "os"]).unwrap(), OutputFormat::Json);
+        assert_eq!(requested_format(&["--format", "Markdown", "os"]).unwrap(), OutputFormat::Markdown);
+        assert_eq!(requested_format(&["-o", "json", "os"]).unwrap(), OutputFormat::Json);
+    }
+
+    #[test]
+    fn nagios_report_is_silent_on_quiet_runs() {
+        let result = || -> Result<CommandResult> { Err(anyhow::anyhow!("no such disk")) };
+
+        let cli = Cli::try_parse_from(["my", "-o", "nagios", "disks"]).unwrap();
+        let (status, line) = cli.nagios_report(&result());
+        assert_eq!(status, nagios::Status::Unknown);
+        assert_eq!(line.as_deref(), Some("UNKNOWN: no such disk"));
+
+        let quiet = Cli::try_parse_from(["my", "-o", "nagios", "--quiet", "disks"]).unwrap();
+        let (status, line) = quiet.nagios_report(&result());
+        assert_eq!(status, nagios::Status::Unknown);
+        assert_eq!(line, None);
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt::Display;
+
+/// The status of a nagios check, as defined by the nagios plugin guidelines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl Status {
+    /// Returns the conventional exit code of the status.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Status::Ok => 0,
+            Status::Warning => 1,
+            Status::Critical => 2,
+            Status::Unknown => 3,
+        }
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Ok => write!(f, "OK"),
+            Status::Warning => write!(f, "WARNING"),
+            Status::Critical => write!(f, "CRITICAL"),
+            Status::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+/// The user-provided thresholds of a check, overriding the check's defaults.
+pub struct Thresholds {
+    pub warning: Option<f64>,
+    pub critical: Option<f64>,
+}
+
+/// A set of metrics to evaluate against warning and critical thresholds.
+///
+/// A metric whose value is greater than or equal to a threshold triggers
+/// the threshold's status.
+pub struct Check {
+    pub metrics: Vec<Metric>,
+
+    /// The default warning threshold.
+    pub warning: f64,
+
+    /// The default critical threshold.
+    pub critical: f64,
+}
+
+/// A measured value, reported as nagios performance data.
+pub struct Metric {
+    pub label: String,
+    pub value: f64,
+    pub unit: &'static str,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Metric {
+    /// Creates a metric holding a percentage, rounded to one decimal.
+    pub fn percentage(label: &str, value: f64) -> Metric {
+        Metric {
+            label: label.to_string(),
+            value: (value * 10.0).round() / 10.0,
+            unit: "%",
+            min: Some(0.0),
+            max: Some(100.0),
+        }
+    }
+
+    /// Creates a metric holding a duration in seconds, rounded to four decimals.
+    pub fn seconds(label: &str, value: f64) -> Metric {
+        Metric {
+            label: label.to_string(),
+            value: (value * 10000.0).round() / 10000.0,
+            unit: "s",
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Formats the metric as nagios performance data:
+    /// `'label'=value[unit];[warn];[crit];[min];[max]`.
+    fn perfdata(&self, warning: f64, critical: f64) -> String {
+        let bound = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+        format!(
+            "{}={}{};{};{};{};{}",
+            perfdata_label(&self.label),
+            self.value,
+            self.unit,
+            warning,
+            critical,
+            bound(self.min),
+            bound(self.max),
+        )
+    }
+}
+
+/// The outcome of a check: its status, and the line reporting it in the
+/// nagios plugin output format, which is left to the caller to print.
+pub struct Report {
+    pub status: Status,
+    pub line: String,
+}
+
+/// Reports the outcome of a check in the nagios plugin output format:
+/// `STATUS: message | perfdata`.
+///
+/// The check's status is the worst status of its metrics. A missing check
+/// is reported as unknown.
+pub fn report(check: Option<Check>, thresholds: &Thresholds) -> Report {
+    let check = match check {
+        Some(check) => check,
+        None => return report_unknown("the command does not support nagios checks"),
+    };
+
+    let warning = thresholds.warning.unwrap_or(check.warning);
+    let critical = thresholds.critical.unwrap_or(check.critical);
+
+    let status = check
+        .metrics
+        .iter()
+        .map(|metric| match metric.value {
+            value if value >= critical => Status::Critical,
+            value if value >= warning => Status::Warning,
+            _ => Status::Ok,
+        })
+        .max()
+        .unwrap_or(Status::Ok);
+
+    let message = check
+        .metrics
+        .iter()
+        .map(|metric| format!("{} {}{}", metric.label, metric.value, metric.unit))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let perfdata = check
+        .metrics
+        .iter()
+        .map(|metric| metric.perfdata(warning, critical))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    Report {
+        status,
+        line: format!("{}: {} | {}", status, message, perfdata),
+    }
+}
+
+/// Reports an unknown status, along with the reason the check could not be performed.
+pub fn report_unknown(reason: &str) -> Report {
+    Report {
+        status: Status::Unknown,
+        line: format!("{}: {}", Status::Unknown, reason),
+    }
+}
+
+/// Quotes a performance data label if it holds characters other than
+/// alphanumerics, underscores, dashes, dots and slashes.
+fn perfdata_label(label: &str) -> String {
+    if label.chars().all(|c| c.is_ascii_alphanumeric() || "_-./".contains(c)) {
+        label.to_string()
+    } else {
+        format!("'{}'", label.replace('\'', "''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(values: &[f64]) -> Check {
+        Check {
+            metrics: values.iter().enumerate().map(|(index, value)| Metric::percentage(&format!("m{}", index), *value)).collect(),
+            warning: 80.0,
+            critical: 90.0,
+        }
+    }
+
+    #[test]
+    fn report_takes_the_worst_status_of_the_metrics() {
+        let defaults = Thresholds { warning: None, critical: None };
+
+        let report = report(Some(check(&[50.0, 85.0])), &defaults);
+        assert_eq!(report.status, Status::Warning);
+        assert_eq!(report.line, "WARNING: m0 50%, m1 85% | m0=50%;80;90;0;100 m1=85%;80;90;0;100");
+
+        assert_eq!(super::report(Some(check(&[95.0, 10.0])), &defaults).status, Status::Critical);
+        assert_eq!(super::report(Some(check(&[10.0])), &defaults).status, Status::Ok);
+    }
+
+    #[test]
+    fn report_applies_the_user_thresholds() {
+        let thresholds = Thresholds { warning: Some(40.0), critical: Some(60.0) };
+
+        assert_eq!(report(Some(check(&[50.0])), &thresholds).status, Status::Warning);
+    }
+
+    #[test]
+    fn report_of_a_missing_check_is_unknown() {
+        let report = report(None, &Thresholds { warning: None, critical: None });
+
+        assert_eq!(report.status, Status::Unknown);
+        assert_eq!(report.line, "UNKNOWN: the command does not support nagios checks");
+    }
+}
@@ -1,45 +1,44 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Formatter};
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::ValueEnum;
-use serde::{Deserialize, Serialize};
+use colored::*;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sysinfo::{NetworkExt, NetworksExt, RefreshKind, System, SystemExt};
 use tokio::task::spawn_blocking;
+use trust_dns_proto::op::{Message, MessageType, OpCode, Query};
+use trust_dns_proto::rr::{DNSClass, Name, RData, RecordType};
 use trust_dns_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
 use trust_dns_resolver::{system_conf, TokioAsyncResolver, TokioHandle};
+use unicode_width::UnicodeWidthStr;
 
-#[derive(Serialize)]
-pub struct IpReport {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    public: Option<IpAddr>,
-
-    #[serde(skip_serializing_if = "Option::is_none")]
-    local: Option<IpAddr>,
-}
-
-impl Display for IpReport {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if let Some(public) = &self.public {
-            write!(f, "public\t{}", public)?;
-        }
-
-        if let Some(local) = &self.local {
-            write!(f, "local\t{}", local)?;
-        }
-
-        Ok(())
-    }
-}
+use crate::error::{self, ErrorCode};
+use crate::format::{human_readable_count, pad_right, CountStyle};
+use crate::theme;
 
 /// A categorized IP address.
-#[derive(Serialize, Deserialize, Debug)]
+///
+/// Besides the address and its category, its serialized form holds the
+/// address' scope, and whether it is private, as derived from the address.
+/// The resolver a public address was looked up from is only serialized for
+/// public addresses.
+#[derive(Deserialize, Debug)]
 pub struct Ip {
     /// The IP address.
-    #[serde(rename(serialize = "ip", deserialize = "ip"))]
+    #[serde(rename = "ip")]
     pub address: IpAddr,
 
     /// The category of the IP address.
     pub category: IpCategory,
+
+    /// The resolver or endpoint the public IP address was looked up from,
+    /// such as opendns. None for local IP addresses.
+    #[serde(default)]
+    pub via: Option<String>,
 }
 
 impl Display for Ip {
@@ -48,6 +47,98 @@ impl Display for Ip {
     }
 }
 
+impl Serialize for Ip {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let scope = IpScope::of(&self.address);
+
+        let mut state = serializer.serialize_struct("Ip", 5)?;
+        state.serialize_field("ip", &self.address)?;
+        state.serialize_field("category", &self.category)?;
+        match &self.via {
+            Some(via) => state.serialize_field("via", via)?,
+            None => state.skip_field("via")?,
+        }
+        state.serialize_field("is_private", &scope.is_private())?;
+        state.serialize_field("scope", &scope)?;
+        state.end()
+    }
+}
+
+/// The scope of an IP address, as defined by the IANA special-purpose address registries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpScope {
+    /// A globally routable address.
+    Global,
+
+    /// A private address: RFC 1918, shared address space (CGNAT), or IPv6 unique local.
+    Private,
+
+    /// A loopback address.
+    Loopback,
+
+    /// A link-local address.
+    LinkLocal,
+
+    /// Any other special-purpose address, such as documentation, multicast,
+    /// broadcast, or unspecified addresses.
+    Reserved,
+}
+
+impl IpScope {
+    /// Classifies an IP address into its scope.
+    ///
+    /// IPv4-mapped IPv6 addresses are classified as the IPv4 address they map.
+    pub fn of(address: &IpAddr) -> IpScope {
+        match address {
+            IpAddr::V4(address) => {
+                let octets = address.octets();
+
+                match octets {
+                    _ if address.is_loopback() => IpScope::Loopback,
+                    _ if address.is_link_local() => IpScope::LinkLocal,
+                    _ if address.is_private() => IpScope::Private,
+                    // Shared address space, 100.64.0.0/10
+                    [100, second, ..] if second & 0b1100_0000 == 64 => IpScope::Private,
+                    // Benchmarking, 198.18.0.0/15
+                    [198, 18 | 19, ..] => IpScope::Reserved,
+                    // "This network", 0.0.0.0/8, and reserved for future use, 240.0.0.0/4
+                    [0, ..] | [240..=255, ..] => IpScope::Reserved,
+                    _ if address.is_documentation() || address.is_multicast() => IpScope::Reserved,
+                    _ => IpScope::Global,
+                }
+            }
+            IpAddr::V6(address) => {
+                if let Some(mapped) = address.to_ipv4_mapped() {
+                    return IpScope::of(&IpAddr::V4(mapped));
+                }
+
+                let first_segment = address.segments()[0];
+
+                match first_segment {
+                    _ if address.is_loopback() => IpScope::Loopback,
+                    // Link-local unicast, fe80::/10
+                    _ if first_segment & 0xffc0 == 0xfe80 => IpScope::LinkLocal,
+                    // Unique local, fc00::/7
+                    _ if first_segment & 0xfe00 == 0xfc00 => IpScope::Private,
+                    // Documentation, 2001:db8::/32
+                    0x2001 if address.segments()[1] == 0xdb8 => IpScope::Reserved,
+                    _ if address.is_unspecified() || address.is_multicast() => IpScope::Reserved,
+                    _ => IpScope::Global,
+                }
+            }
+        }
+    }
+
+    /// Returns whether the scope is anything but global.
+    pub fn is_private(self) -> bool {
+        self != IpScope::Global
+    }
+}
+
 /// Queries the public IP address from the provided dns server.
 /// Only an IPv4 address is returned.
 ///
@@ -80,7 +171,7 @@ pub async fn query_public_ip(dns_server_host: &str, dns_server_port: u16) -> Res
 
     let mut resolver_opts = ResolverOpts::default();
     resolver_opts.ndots = 1;
-    resolver_opts.timeout = std::time::Duration::from_secs(5);
+    resolver_opts.timeout = PUBLIC_IP_TIMEOUT;
 
     // Create the resolver
     let resolver = TokioAsyncResolver::new(resolver_config, resolver_opts, TokioHandle)?;
@@ -93,88 +184,428 @@ pub async fn query_public_ip(dns_server_host: &str, dns_server_port: u16) -> Res
     Ok(IpAddr::V4(*ipv4))
 }
 
-/// The default DNS server port.
+/// Queries the public IP address from the provided dns server, sending the
+/// query through the provided network interface.
+/// Only an IPv4 address is returned.
 ///
-/// This constant is used as a default to query the public IP address
-pub const DNS_DEFAULT_PORT: u16 = 53;
+/// Binding the query's socket to an interface forces it out of that
+/// interface's link, regardless of the routing table, which matters on
+/// hosts with several network interfaces.
+///
+/// # Arguments
+///
+/// * `dns_server_host` - The DNS server host to query the public IP address from.
+/// * `dns_server_port` - The DNS server port to query the public IP address from.
+/// * `interface` - The name of the network interface to send the query through.
+///
+/// # Returns
+///
+/// The public IP address.
+///
+/// # Errors
+///
+/// If the DNS server host cannot be parsed, if the interface does not exist,
+/// if the socket cannot be bound to the interface, which requires the
+/// `CAP_NET_RAW` capability on older kernels, or if the DNS server cannot
+/// be queried.
+///
+/// # Examples
+///
+/// ```
+/// let public_ip = network::query_public_ip_via(network::OPENDNS_SERVER_HOST, 53, "eth1").await.unwrap();
+/// println!("public ip: {}", public_ip);
+/// ```
+#[cfg(target_os = "linux")]
+pub async fn query_public_ip_via(dns_server_host: &str, dns_server_port: u16, interface: &str) -> Result<IpAddr> {
+    use std::os::unix::io::AsRawFd;
 
-/// The openDNS server host.
+    let dns_server_addr = SocketAddr::new(dns_server_host.parse()?, dns_server_port);
+
+    if !interfaces(false).await?.iter().any(|i| i.name == interface) {
+        return Err(error::categorized(
+            ErrorCode::NotFound,
+            format!("no network interface named {} was found", interface),
+        ));
+    }
+
+    let interface = interface.to_string();
+    spawn_blocking(move || {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+
+        // Bind the socket to the interface, so that the query leaves through it
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_BINDTODEVICE,
+                interface.as_ptr() as *const libc::c_void,
+                interface.len() as libc::socklen_t,
+            )
+        };
+        if result != 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("binding the dns query's socket to interface {} failed", interface));
+        }
+
+        let query = Query::query(Name::from_ascii("myip.opendns.com.")?, RecordType::A);
+        let response = exchange_dns_query(socket, dns_server_addr, query)?;
+
+        response
+            .answers()
+            .iter()
+            .find_map(|record| match record.data() {
+                Some(RData::A(ip)) => Some(IpAddr::V4(*ip)),
+                _ => None,
+            })
+            .ok_or_else(|| error::categorized(ErrorCode::DnsFailure, "the dns server's response holds no IPv4 address"))
+    })
+    .await?
+}
+
+/// Sends a single DNS query to a DNS server over UDP, and returns its response.
+fn exchange_dns_query(socket: UdpSocket, dns_server_addr: SocketAddr, query: Query) -> Result<Message> {
+    socket.set_read_timeout(Some(PUBLIC_IP_TIMEOUT))?;
+    socket.connect(dns_server_addr)?;
+
+    let mut message = Message::new();
+    message
+        .set_id(random_query_id())
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(true)
+        .add_query(query);
+    socket.send(&message.to_vec()?)?;
+
+    let mut buffer = [0u8; 512];
+    let length = socket.recv(&mut buffer)?;
+    let response = Message::from_vec(&buffer[..length])?;
+    if response.id() != message.id() {
+        return Err(error::categorized(ErrorCode::DnsFailure, "the dns server responded to another query"));
+    }
+
+    Ok(response)
+}
+
+/// Returns a random DNS query ID, so that a spoofed response cannot be
+/// forged by guessing it.
 ///
-/// This constant is used as a default to query the public IP address
-pub const OPENDNS_SERVER_HOST: &str = "208.67.222.222";
+/// The randomness comes from the keys the standard library seeds its hash
+/// maps with, which differ for each `RandomState`.
+fn random_query_id() -> u16 {
+    use std::hash::{BuildHasher, Hasher};
 
-/// Lists the DNS servers from the system configuration.
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+    hasher.finish() as u16
+}
+
+/// A DNS server reporting the public IP address of the clients querying it.
+pub struct PublicIpResolver {
+    /// The name of the DNS server's operator.
+    pub name: &'static str,
+
+    /// The IP address of the DNS server.
+    pub host: &'static str,
+
+    /// The name to query the public IP address with.
+    pub query_name: &'static str,
+
+    /// The type of record holding the public IP address: A or TXT.
+    pub record_type: RecordType,
+
+    /// The class of the query: IN, or CH for Cloudflare's whoami record.
+    pub query_class: DNSClass,
+}
+
+/// The DNS servers queried for the public IP address when looking for a consensus.
+pub const PUBLIC_IP_RESOLVERS: &[PublicIpResolver] = &[
+    PublicIpResolver {
+        name: "opendns",
+        host: OPENDNS_SERVER_HOST,
+        query_name: "myip.opendns.com.",
+        record_type: RecordType::A,
+        query_class: DNSClass::IN,
+    },
+    PublicIpResolver {
+        name: "google",
+        host: "216.239.32.10",
+        query_name: "o-o.myaddr.l.google.com.",
+        record_type: RecordType::TXT,
+        query_class: DNSClass::IN,
+    },
+    PublicIpResolver {
+        name: "cloudflare",
+        host: "1.1.1.1",
+        query_name: "whoami.cloudflare.",
+        record_type: RecordType::TXT,
+        query_class: DNSClass::CH,
+    },
+];
+
+/// Queries the public IP address from the provided resolver.
+///
+/// # Arguments
 ///
-/// The DNS servers are returned as a list of IP addresses.
-/// The DNS servers are deduplicated.
-/// The DNS servers are returned in the order they are defined in the system configuration.
+/// * `resolver` - The DNS server to query the public IP address from, along with the query it answers.
 ///
 /// # Returns
 ///
-/// The DNS servers:
-///   * The DNS servers are returned as a list of IP addresses.
-///   * The DNS servers are deduplicated.
-///   * The DNS servers are returned in the order they are defined in the system configuration.
+/// The public IP address, as held by the first A record, or the first TXT
+/// record holding an IP address.
 ///
 /// # Errors
 ///
-/// If the system configuration cannot be read.
+/// If the DNS server cannot be queried, or if its response holds no IP address.
 ///
 /// # Examples
 ///
 /// ```
-/// let dns_servers = ip::list_dns_servers().unwrap();
-/// println!("dns servers: {:?}", dns_servers);
+/// let public_ip = network::query_public_ip_from(&network::PUBLIC_IP_RESOLVERS[0]).await.unwrap();
+/// println!("public ip: {}", public_ip);
 /// ```
-pub async fn list_dns_servers() -> Result<Vec<String>> {
-    let (conf, _) = system_conf::read_system_conf()?;
-    let mut nameservers = conf
-        .name_servers()
+pub async fn query_public_ip_from(resolver: &PublicIpResolver) -> Result<IpAddr> {
+    let dns_server_addr = SocketAddr::new(resolver.host.parse()?, DNS_DEFAULT_PORT);
+
+    let mut query = Query::query(Name::from_ascii(resolver.query_name)?, resolver.record_type);
+    query.set_query_class(resolver.query_class);
+
+    spawn_blocking(move || {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        let response = exchange_dns_query(socket, dns_server_addr, query)?;
+
+        response
+            .answers()
+            .iter()
+            .find_map(|record| match record.data() {
+                Some(RData::A(ip)) => Some(IpAddr::V4(*ip)),
+                Some(RData::TXT(txt)) => txt
+                    .txt_data()
+                    .iter()
+                    .find_map(|data| std::str::from_utf8(data).ok()?.parse().ok()),
+                _ => None,
+            })
+            .ok_or_else(|| error::categorized(ErrorCode::DnsFailure, "the dns server's response holds no IP address"))
+    })
+    .await?
+}
+
+/// Queries the public IP address from each of the public IP resolvers
+/// concurrently, and looks for a consensus among their answers.
+///
+/// # Returns
+///
+/// Each resolver's answer, in the order of `PUBLIC_IP_RESOLVERS`, along
+/// with the consensus. A resolver failing to answer does not fail the lookup.
+///
+/// # Errors
+///
+/// If none of the resolvers answered.
+///
+/// # Examples
+///
+/// ```
+/// let consensus = network::query_public_ip_consensus().await.unwrap();
+/// println!("public ip: {:?}", consensus.consensus);
+/// ```
+pub async fn query_public_ip_consensus() -> Result<PublicIpConsensus> {
+    let (opendns, google, cloudflare) = tokio::join!(
+        query_public_ip_from(&PUBLIC_IP_RESOLVERS[0]),
+        query_public_ip_from(&PUBLIC_IP_RESOLVERS[1]),
+        query_public_ip_from(&PUBLIC_IP_RESOLVERS[2]),
+    );
+
+    let answers: Vec<ResolverAnswer> = PUBLIC_IP_RESOLVERS
         .iter()
-        .map(|ns| {
-            ns.socket_addr
-                .to_string()
-                .splitn(2, ':')
-                .next()
-                .unwrap()
-                .to_owned()
+        .zip([opendns, google, cloudflare])
+        .map(|(resolver, answer)| match answer {
+            Ok(ip) => ResolverAnswer {
+                resolver: resolver.name.to_string(),
+                ip: Some(ip),
+                error: None,
+            },
+            Err(err) => ResolverAnswer {
+                resolver: resolver.name.to_string(),
+                ip: None,
+                error: Some(format!("{:#}", err)),
+            },
         })
-        .collect::<Vec<_>>();
+        .collect();
 
-    nameservers.dedup();
+    if answers.iter().all(|answer| answer.ip.is_none()) {
+        let failures = answers
+            .iter()
+            .map(|answer| format!("{}: {}", answer.resolver, answer.error.as_deref().unwrap_or("no answer")))
+            .collect::<Vec<String>>();
 
-    Ok(nameservers)
+        return Err(error::categorized(
+            ErrorCode::DnsFailure,
+            format!("no resolver answered; {}", failures.join("; ")),
+        ));
+    }
+
+    Ok(PublicIpConsensus {
+        consensus: consensus_of(&answers),
+        answers,
+    })
 }
 
-/// Holds the category of an IP address. The category can be public, local or any.
-#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize, ValueEnum)]
-pub enum IpCategory {
-    #[clap(name = "public")]
-    Public,
+/// Returns the IP address answered the most, or None if no address was
+/// answered strictly more often than every other one.
+fn consensus_of(answers: &[ResolverAnswer]) -> Option<IpAddr> {
+    let mut counts: BTreeMap<IpAddr, usize> = BTreeMap::new();
+    for ip in answers.iter().filter_map(|answer| answer.ip) {
+        *counts.entry(ip).or_default() += 1;
+    }
 
-    #[clap(name = "local")]
-    Local,
+    let mut counts: Vec<(IpAddr, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
 
-    #[clap(name = "any")]
-    Any,
+    match counts.as_slice() {
+        [(ip, _)] => Some(*ip),
+        [(ip, first), (_, second), ..] if first > second => Some(*ip),
+        _ => None,
+    }
 }
 
-impl Display for IpCategory {
+/// The public IP address as answered by a resolver.
+#[derive(Deserialize, Serialize)]
+pub struct ResolverAnswer {
+    /// The name of the resolver.
+    pub resolver: String,
+
+    /// The public IP address the resolver answered, if it did.
+    pub ip: Option<IpAddr>,
+
+    /// The reason the resolver failed to answer, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The public IP addresses answered by several resolvers, along with the
+/// address most of them agree on.
+///
+/// Disagreeing answers are kept as-is, so that NAT hairpinning or multiple
+/// egress paths are visible rather than hidden by the consensus.
+#[derive(Deserialize, Serialize)]
+pub struct PublicIpConsensus {
+    /// The IP address answered strictly more often than every other one, if any.
+    pub consensus: Option<IpAddr>,
+
+    /// Each resolver's answer.
+    pub answers: Vec<ResolverAnswer>,
+}
+
+impl Display for PublicIpConsensus {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            IpCategory::Public => write!(f, "public"),
-            IpCategory::Local => write!(f, "local"),
-            IpCategory::Any => write!(f, "*"),
+        let palette = theme::palette();
+        let name_width = self.answers.iter().map(|answer| answer.resolver.width()).max().unwrap_or(0);
+
+        match self.consensus {
+            Some(consensus) => writeln!(f, "consensus: {}", consensus.to_string().bold())?,
+            None => writeln!(f, "consensus: {}", "none".color(palette.warning))?,
         }
+
+        let lines = self
+            .answers
+            .iter()
+            .map(|answer| {
+                let value = match (&answer.ip, &answer.error) {
+                    (Some(ip), _) if self.consensus == Some(*ip) => ip.to_string().normal(),
+                    (Some(ip), _) => ip.to_string().color(palette.warning),
+                    (None, error) => format!("failed: {}", error.as_deref().unwrap_or("no answer")).color(palette.critical),
+                };
+
+                format!("{}  {}", pad_right(&answer.resolver, name_width), value)
+            })
+            .collect::<Vec<String>>();
+
+        write!(f, "{}", lines.join("\n"))
     }
 }
 
-/// Lists the network interfaces of the system.
+/// The default destination the source IP address is looked up for.
+pub const SOURCE_IP_DEFAULT_DESTINATION: &str = "8.8.8.8";
+
+/// Looks up the local IP address the system would send packets to the
+/// provided destination from.
+///
+/// The kernel picks the source address from its routing table, which may
+/// differ from the primary local IP address on hosts with several network
+/// interfaces or VPNs. Connecting a UDP socket has the kernel pick it
+/// without sending any packet.
+///
+/// # Arguments
+///
+/// * `destination` - The address the packets would be sent to.
 ///
 /// # Returns
 ///
-/// A vector holding the network interfaces.
-/// The network interfaces are returned in the order they are defined in the system configuration.
+/// The destination, along with the source IP address.
+///
+/// # Errors
+///
+/// If the destination is not routable.
+///
+/// # Examples
+///
+/// ```
+/// let source = network::source_ip("8.8.8.8".parse().unwrap()).unwrap();
+/// println!("source ip: {}", source.source_ip);
+/// ```
+pub fn source_ip(destination: IpAddr) -> Result<SourceIp> {
+    let bind_address: IpAddr = match destination {
+        IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        IpAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+
+    let socket = UdpSocket::bind((bind_address, 0)).with_context(|| "binding the udp socket failed")?;
+    socket
+        .connect((destination, DNS_DEFAULT_PORT))
+        .with_context(|| format!("no route to {}", destination))?;
+
+    Ok(SourceIp {
+        destination,
+        source_ip: socket.local_addr()?.ip(),
+    })
+}
+
+/// The local IP address the system sends packets to a destination from.
+#[derive(Deserialize, Serialize)]
+pub struct SourceIp {
+    /// The address the packets would be sent to.
+    pub destination: IpAddr,
+
+    /// The local IP address the packets would be sent from.
+    pub source_ip: IpAddr,
+}
+
+impl Display for SourceIp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source_ip)
+    }
+}
+
+/// Queries the public IP address through the provided network interface.
+///
+/// Binding a socket to a network interface is only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub async fn query_public_ip_via(_dns_server_host: &str, _dns_server_port: u16, _interface: &str) -> Result<IpAddr> {
+    Err(error::categorized(
+        ErrorCode::UnsupportedPlatform,
+        "binding the public ip query to a network interface is not supported on this platform",
+    ))
+}
+
+/// Resolves the name associated with an IP address, using the system's DNS configuration.
+///
+/// # Arguments
+///
+/// * `ip` - The IP address to resolve the name of.
+///
+/// # Returns
+///
+/// The first name the IP address resolves to, without its trailing dot,
+/// or None if the IP address does not resolve to any name.
 ///
 /// # Errors
 ///
@@ -183,34 +614,2235 @@ impl Display for IpCategory {
 /// # Examples
 ///
 /// ```
-/// let interfaces = ip::list_interfaces().unwrap();
-/// println!("interfaces: {:?}", interfaces);
+/// let name = network::reverse_lookup("192.168.1.20".parse().unwrap()).await.unwrap();
+/// println!("name: {:?}", name);
 /// ```
-pub async fn interfaces() -> Result<Vec<Interface>> {
-    spawn_blocking(|| get_if_addrs::get_if_addrs())
-        .await??
-        .into_iter()
-        .try_fold(Vec::new(), |mut acc, i| {
-            acc.push(Interface {
-                name: i.name.clone(),
-                ip: i.ip().to_string(),
-            });
-            Ok(acc)
-        })
+pub async fn reverse_lookup(ip: IpAddr) -> Result<Option<String>> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+
+    let name = match resolver.reverse_lookup(ip).await {
+        Ok(response) => response
+            .iter()
+            .next()
+            .map(|name| name.to_utf8().trim_end_matches('.').to_string()),
+        Err(_) => None,
+    };
+
+    Ok(name)
 }
 
-/// A network interface.
-#[derive(Serialize)]
-pub struct Interface {
-    /// The name of the network interface.
-    name: String,
+/// Queries the public IP address from the provided HTTPS endpoint.
+///
+/// The endpoint is expected to respond with the caller's IP address
+/// as a plain text body, as `https://api.ipify.org` does.
+///
+/// # Arguments
+///
+/// * `url` - The URL of the HTTPS endpoint to query the public IP address from.
+/// * `timeout` - The maximum duration of the whole request.
+///
+/// # Returns
+///
+/// The public IP address.
+///
+/// # Errors
+///
+/// If the endpoint cannot be reached, responds with a non-200 status,
+/// or responds with a body that is not an IP address.
+///
+/// # Examples
+///
+/// ```
+/// let public_ip = network::query_public_ip_https(network::IPIFY_URL, Duration::from_secs(5)).await.unwrap();
+/// println!("public ip: {}", public_ip);
+/// ```
+pub async fn query_public_ip_https(url: &str, timeout: Duration) -> Result<IpAddr> {
+    let url = url.to_string();
 
-    /// The IP address of the network interface.
-    ip: String,
+    spawn_blocking(move || {
+        let response = match ureq::get(&url).timeout(timeout).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, _)) => {
+                anyhow::bail!("{} responded with unexpected status {}", url, status)
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let body = response
+            .into_string()
+            .with_context(|| format!("reading the response body of {} failed", url))?;
+
+        body.trim()
+            .parse::<IpAddr>()
+            .with_context(|| format!("{} responded with {:?}, which is not an IP address", url, body.trim()))
+    })
+    .await?
 }
 
-impl Display for Interface {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\t{}", self.name, self.ip)
+/// The method used to look up the public IP address.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum PublicIpMethod {
+    /// Query the OpenDNS resolver for the myip.opendns.com record.
+    #[clap(name = "dns")]
+    Dns,
+
+    /// Query an HTTPS endpoint responding with the caller's IP address.
+    #[clap(name = "https")]
+    Https,
+}
+
+impl PublicIpMethod {
+    /// Returns the name of the resolver or endpoint the method looks the
+    /// public IP address up from.
+    pub fn via(self) -> &'static str {
+        match self {
+            PublicIpMethod::Dns => "opendns",
+            PublicIpMethod::Https => "ipify",
+        }
+    }
+}
+
+/// The ipify HTTPS endpoint.
+///
+/// This constant is used as a default to query the public IP address over HTTPS
+pub const IPIFY_URL: &str = "https://api.ipify.org";
+
+/// The default timeout of the public IP address lookups.
+pub const PUBLIC_IP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The default DNS server port.
+///
+/// This constant is used as a default to query the public IP address
+pub const DNS_DEFAULT_PORT: u16 = 53;
+
+/// The openDNS server host.
+///
+/// This constant is used as a default to query the public IP address
+pub const OPENDNS_SERVER_HOST: &str = "208.67.222.222";
+
+/// Lists the DNS servers from the system configuration.
+///
+/// The DNS servers are returned in the order they are defined in the system
+/// configuration, along with the port and protocol they are queried over.
+///
+/// # Returns
+///
+/// The DNS servers:
+///   * Each DNS server holds its address, port and protocol.
+///   * The DNS servers are deduplicated: as the system configuration lists
+///     each resolver over both UDP and TCP, entries sharing an address and
+///     port are collapsed onto the first one.
+///   * The DNS servers are returned in the order they are defined in the system configuration.
+///
+/// # Errors
+///
+/// If the system configuration cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// let dns_servers = ip::list_dns_servers().unwrap();
+/// println!("dns servers: {:?}", dns_servers);
+/// ```
+pub async fn list_dns_servers() -> Result<Vec<DnsServer>> {
+    let (conf, _) = system_conf::read_system_conf()?;
+    let mut nameservers: Vec<DnsServer> = Vec::new();
+
+    for ns in conf.name_servers() {
+        let server = DnsServer {
+            address: ns.socket_addr.ip(),
+            port: ns.socket_addr.port(),
+            protocol: ns.protocol.to_string(),
+        };
+
+        if !nameservers
+            .iter()
+            .any(|known| known.address == server.address && known.port == server.port)
+        {
+            nameservers.push(server);
+        }
+    }
+
+    Ok(nameservers)
+}
+
+/// A DNS server from the system configuration.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DnsServer {
+    /// The IP address of the DNS server.
+    pub address: IpAddr,
+
+    /// The port the DNS server is queried on.
+    pub port: u16,
+
+    /// The protocol the DNS server is queried over: udp, tcp, tls, https or quic.
+    pub protocol: String,
+}
+
+impl Display for DnsServer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.port == DNS_DEFAULT_PORT && self.protocol == "udp" {
+            return write!(f, "{}", self.address);
+        }
+
+        let protocol = match self.protocol.as_str() {
+            "tls" => "DoT".to_string(),
+            "https" => "DoH".to_string(),
+            "quic" => "DoQ".to_string(),
+            other => other.to_uppercase(),
+        };
+
+        write!(f, "{} ({})", SocketAddr::new(self.address, self.port), protocol)
+    }
+}
+
+/// The DNS servers from the system configuration, in the order they are used.
+///
+/// They are serialized as an array of DNS servers. When indexed, each DNS
+/// server additionally holds its position in the resolution order, so that
+/// consumers don't have to rely on the array's order.
+pub struct DnsServers {
+    pub servers: Vec<DnsServer>,
+    pub indexed: bool,
+}
+
+/// A DNS server, along with its position in the resolution order.
+#[derive(Serialize)]
+struct IndexedDnsServer<'a> {
+    order: usize,
+
+    #[serde(flatten)]
+    server: &'a DnsServer,
+}
+
+impl Display for DnsServers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.servers
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>()
+                .join("\n")
+        )
+    }
+}
+
+impl Serialize for DnsServers {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !self.indexed {
+            return self.servers.serialize(serializer);
+        }
+
+        serializer.collect_seq(
+            self.servers
+                .iter()
+                .enumerate()
+                .map(|(order, server)| IndexedDnsServer { order, server }),
+        )
+    }
+}
+
+/// A DNS server, as serialized with or without its position in the resolution order.
+#[derive(Deserialize)]
+struct SerializedDnsServer {
+    order: Option<usize>,
+
+    #[serde(flatten)]
+    server: DnsServer,
+}
+
+impl<'de> Deserialize<'de> for DnsServers {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut servers = Vec::<SerializedDnsServer>::deserialize(deserializer)?;
+        let indexed = servers.iter().any(|server| server.order.is_some());
+        servers.sort_by_key(|server| server.order);
+
+        Ok(DnsServers {
+            servers: servers.into_iter().map(|server| server.server).collect(),
+            indexed,
+        })
+    }
+}
+
+/// The number of DNS servers from the system configuration.
+#[derive(Deserialize, Serialize)]
+pub struct DnsServerCount {
+    pub dns_server_count: usize,
+}
+
+impl Display for DnsServerCount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.dns_server_count)
+    }
+}
+
+/// Holds the category of an IP address. The category can be public, local or any.
+///
+/// The any category is only ever used to filter the IP addresses to look up:
+/// it selects both the public and local addresses, which each carry their
+/// own concrete category.
+///
+/// It is serialized in snake_case, as all the serialized values are. The
+/// capitalized names the public and local categories were formerly
+/// serialized as are still deserialized.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum IpCategory {
+    #[clap(name = "public")]
+    #[serde(alias = "Public")]
+    Public,
+
+    #[clap(name = "local")]
+    #[serde(alias = "Local")]
+    Local,
+
+    #[clap(name = "any")]
+    Any,
+}
+
+impl Display for IpCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpCategory::Public => write!(f, "public"),
+            IpCategory::Local => write!(f, "local"),
+            IpCategory::Any => write!(f, "any"),
+        }
+    }
+}
+
+/// Lists the network interfaces of the system.
+///
+/// When `detailed` is set, the addresses the default routes go through, and
+/// how the addresses were assigned, are detected as well, which reads the
+/// routing table and the DHCP leases, and on some platforms runs commands.
+/// Only the interfaces listing renders them.
+///
+/// # Returns
+///
+/// A vector holding the network interfaces.
+/// The network interfaces are returned in the order they are defined in the system configuration.
+///
+/// # Errors
+///
+/// If the system configuration cannot be read.
+///
+/// # Examples
+///
+/// ```
+/// let interfaces = network::interfaces(false).await.unwrap();
+/// println!("interfaces: {:?}", interfaces);
+/// ```
+pub async fn interfaces(detailed: bool) -> Result<Vec<Interface>> {
+    let mut system = System::new_with_specifics(RefreshKind::new().with_networks_list());
+    system.refresh_networks_list();
+
+    let mac_addresses = system
+        .networks()
+        .iter()
+        .filter(|(_, network)| !network.mac_address().is_unspecified())
+        .map(|(name, network)| (name.clone(), network.mac_address().to_string()))
+        .collect::<BTreeMap<String, String>>();
+
+    let counters = system
+        .networks()
+        .iter()
+        .map(|(name, network)| {
+            let counters = InterfaceCounters {
+                packets_received: network.total_packets_received(),
+                packets_transmitted: network.total_packets_transmitted(),
+                errors_received: network.total_errors_on_received(),
+                errors_transmitted: network.total_errors_on_transmitted(),
+            };
+            (name.clone(), counters)
+        })
+        .collect::<BTreeMap<String, InterfaceCounters>>();
+
+    let default_routes = if detailed {
+        spawn_blocking(read_default_routes).await?
+    } else {
+        DefaultRoutes::default()
+    };
+    let interface_flags = spawn_blocking(read_interface_flags).await?;
+
+    // How addresses were assigned is read from files, or from commands on
+    // macOS: the files are read once, along with the addresses, and looked
+    // up for each interface
+    let (addresses, assignments) = spawn_blocking(move || -> std::io::Result<_> {
+        let addresses = get_if_addrs::get_if_addrs()?;
+        if !detailed {
+            return Ok((addresses, BTreeMap::new()));
+        }
+
+        let sources = AssignmentSources::read();
+        let assignments = addresses
+            .iter()
+            .filter(|i| !i.is_loopback())
+            .map(|i| i.name.clone())
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .map(|name| {
+                let assignment = sources.of(&name);
+                (name, assignment)
+            })
+            .collect::<BTreeMap<String, InterfaceAssignment>>();
+
+        Ok((addresses, assignments))
+    })
+    .await??;
+
+    let mut interfaces = addresses
+        .into_iter()
+        .try_fold(Vec::new(), |mut acc, i| {
+            let netmask = match &i.addr {
+                get_if_addrs::IfAddr::V4(addr) => IpAddr::V4(addr.netmask),
+                get_if_addrs::IfAddr::V6(addr) => IpAddr::V6(addr.netmask),
+            };
+
+            let prefix_len = prefix_len(&netmask);
+            let (network, broadcast) = match i.ip() {
+                IpAddr::V4(ip) => {
+                    let (network, broadcast) = ipv4_network(ip, prefix_len);
+                    (Some(network), Some(broadcast))
+                }
+                IpAddr::V6(_) => (None, None),
+            };
+
+            acc.push(Interface {
+                kind: InterfaceKind::classify(&i.name, i.is_loopback()),
+                assignment: match assignments.get(&i.name) {
+                    Some(assignment) => assignment.assignment(&i.ip()),
+                    None if i.is_loopback() => Assignment::Static,
+                    None => Assignment::Unknown,
+                },
+                mac: mac_addresses.get(&i.name).cloned(),
+                mtu: read_mtu(&i.name),
+                flags: interface_flags.get(&i.name).cloned(),
+                counters: counters.get(&i.name).copied(),
+                ip: i.ip(),
+                netmask,
+                prefix_len,
+                network,
+                broadcast,
+                is_default_route: false,
+                name: i.name,
+            });
+            Ok::<_, anyhow::Error>(acc)
+        })?;
+
+    mark_default_routes(&mut interfaces, &default_routes);
+
+    Ok(interfaces)
+}
+
+/// Marks the addresses the default routes go through.
+///
+/// Only the first address of the family of the default route is marked,
+/// so that a single interface per family carries it.
+fn mark_default_routes(interfaces: &mut [Interface], default_routes: &DefaultRoutes) {
+    for (route_interface, is_family) in [
+        (&default_routes.ipv4, IpAddr::is_ipv4 as fn(&IpAddr) -> bool),
+        (&default_routes.ipv6, IpAddr::is_ipv6),
+    ] {
+        if let Some(interface) = interfaces
+            .iter_mut()
+            .find(|interface| Some(&interface.name) == route_interface.as_ref() && is_family(&interface.ip))
+        {
+            interface.is_default_route = true;
+        }
+    }
+}
+
+/// How an IP address was assigned to a network interface.
+///
+/// The detection is best-effort, and only reports a concrete assignment
+/// when a reliable source tells it:
+///   * Loopback addresses are static on every platform.
+///   * On Linux, addresses found in a DHCP lease of their interface are
+///     assigned through DHCP: the leases of systemd-networkd, under
+///     /run/systemd/netif/leases, of NetworkManager, under
+///     /var/lib/NetworkManager, and of dhclient, under /var/lib/dhcp and
+///     /var/lib/dhclient. Otherwise, the IPv4 address of an interface
+///     configured as `inet static` or `inet dhcp` in /etc/network/interfaces
+///     is assigned accordingly.
+///   * On macOS, the IPv4 address the DHCP packet of its interface offered,
+///     as reported by `ipconfig getpacket`, is assigned through DHCP.
+///
+/// Any other address is unknown: the lack of a lease does not prove an
+/// address is static, as it could have been set by another client.
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Assignment {
+    Dhcp,
+    Static,
+    #[default]
+    Unknown,
+}
+
+/// The sources telling how the addresses of the network interfaces were
+/// assigned, read once for all of them.
+#[derive(Default)]
+struct AssignmentSources {
+    /// The DHCP lease files of NetworkManager and dhclient, by file name.
+    lease_files: Vec<(String, String)>,
+
+    /// The interfaces configuration of /etc/network/interfaces, if any.
+    network_config: Option<String>,
+}
+
+impl AssignmentSources {
+    /// Reads the sources, which is blocking.
+    fn read() -> AssignmentSources {
+        AssignmentSources {
+            lease_files: read_dhcp_lease_files(),
+            network_config: read_network_config(),
+        }
+    }
+
+    /// Reads how the addresses of a network interface were assigned, which
+    /// is blocking, as it may read the interface's own lease.
+    fn of(&self, interface: &str) -> InterfaceAssignment {
+        InterfaceAssignment {
+            lease_addresses: read_dhcp_lease_addresses(interface, &self.lease_files),
+            configured: self
+                .network_config
+                .as_deref()
+                .and_then(|config| parse_configured_assignment(config, interface)),
+        }
+    }
+}
+
+/// Tells how the addresses of a network interface were assigned.
+struct InterfaceAssignment {
+    /// The addresses the DHCP leases of the interface hold.
+    lease_addresses: Vec<IpAddr>,
+
+    /// How the interface's IPv4 address is configured, if it is.
+    configured: Option<Assignment>,
+}
+
+impl InterfaceAssignment {
+    /// Detects how an IP address of the network interface was assigned.
+    fn assignment(&self, ip: &IpAddr) -> Assignment {
+        if ip.is_loopback() {
+            return Assignment::Static;
+        }
+
+        if self.lease_addresses.contains(ip) {
+            return Assignment::Dhcp;
+        }
+
+        match ip {
+            IpAddr::V4(_) => self.configured.unwrap_or_default(),
+            IpAddr::V6(_) => Assignment::Unknown,
+        }
+    }
+}
+
+/// The directories holding the DHCP leases of NetworkManager and dhclient.
+#[cfg(target_os = "linux")]
+const DHCP_LEASE_DIRS: &[&str] = &["/var/lib/NetworkManager", "/var/lib/dhcp", "/var/lib/dhclient"];
+
+/// Reads the lease files of the DHCP lease directories, along with their names.
+#[cfg(target_os = "linux")]
+fn read_dhcp_lease_files() -> Vec<(String, String)> {
+    DHCP_LEASE_DIRS
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if !file_name.ends_with(".lease") && !file_name.ends_with(".leases") {
+                return None;
+            }
+
+            let lease = std::fs::read_to_string(entry.path()).ok()?;
+            Some((file_name, lease))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_dhcp_lease_files() -> Vec<(String, String)> {
+    Vec::new()
+}
+
+/// Reads the addresses the DHCP leases of a network interface hold.
+///
+/// The lease of systemd-networkd is named after the interface's index,
+/// and the others are looked up among the lease files of the DHCP lease
+/// directories.
+#[cfg(target_os = "linux")]
+fn read_dhcp_lease_addresses(interface: &str, lease_files: &[(String, String)]) -> Vec<IpAddr> {
+    let mut addresses = Vec::new();
+
+    if let Ok(index) = std::fs::read_to_string(format!("/sys/class/net/{}/ifindex", interface)) {
+        if let Ok(lease) = std::fs::read_to_string(format!("/run/systemd/netif/leases/{}", index.trim())) {
+            addresses.extend(parse_lease_addresses(&lease, interface, true));
+        }
+    }
+
+    addresses.extend(lease_file_addresses(lease_files, interface));
+    addresses
+}
+
+/// Returns the addresses the lease files hold for a network interface.
+///
+/// The leases of NetworkManager's internal client are named after their
+/// interface, such as internal-<uuid>-eth0.lease, and do not name it within.
+#[cfg(target_os = "linux")]
+fn lease_file_addresses(lease_files: &[(String, String)], interface: &str) -> Vec<IpAddr> {
+    let interface_suffix = format!("-{}.lease", interface);
+
+    lease_files
+        .iter()
+        .flat_map(|(file_name, lease)| parse_lease_addresses(lease, interface, file_name.ends_with(&interface_suffix)))
+        .collect()
+}
+
+/// Parses the addresses a DHCP lease file holds for a network interface.
+///
+/// Two formats are supported: the `KEY=VALUE` lines of systemd-networkd
+/// and NetworkManager's internal client, whose `ADDRESS` is the leased
+/// address, and the `lease { ... }` blocks of dhclient, whose
+/// `fixed-address` is the leased address of the `interface` they name.
+/// The former do not name their interface, and are only parsed when the
+/// lease is known to be the interface's.
+#[cfg(target_os = "linux")]
+fn parse_lease_addresses(lease: &str, interface: &str, is_interface_lease: bool) -> Vec<IpAddr> {
+    let mut addresses = Vec::new();
+    let mut block_interface: Option<String> = None;
+    let mut block_addresses = Vec::new();
+
+    for line in lease.lines().map(str::trim) {
+        let value = |prefix: &str| line.strip_prefix(prefix).map(|value| value.trim().trim_end_matches(';').trim_matches('"').to_string());
+
+        if let Some(address) = value("ADDRESS=") {
+            if is_interface_lease {
+                addresses.extend(address.parse::<IpAddr>().ok());
+            }
+        } else if line.starts_with("lease") && line.ends_with('{') {
+            block_interface = None;
+            block_addresses.clear();
+        } else if let Some(name) = value("interface ") {
+            block_interface = Some(name);
+        } else if let Some(address) = value("fixed-address ") {
+            block_addresses.extend(address.parse::<IpAddr>().ok());
+        } else if line == "}" {
+            if block_interface.as_deref() == Some(interface) {
+                addresses.append(&mut block_addresses);
+            }
+            block_addresses.clear();
+        }
+    }
+
+    addresses
+}
+
+/// Reads the address the DHCP packet of a network interface offered,
+/// through `ipconfig getpacket`, which only succeeds for interfaces
+/// configured through DHCP.
+#[cfg(target_os = "macos")]
+fn read_dhcp_lease_addresses(interface: &str, _lease_files: &[(String, String)]) -> Vec<IpAddr> {
+    let output = match std::process::Command::new("ipconfig").args(["getpacket", interface]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("yiaddr = "))
+        .filter_map(|address| address.trim().parse::<IpAddr>().ok())
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_dhcp_lease_addresses(_interface: &str, _lease_files: &[(String, String)]) -> Vec<IpAddr> {
+    Vec::new()
+}
+
+/// Reads the interfaces configuration of /etc/network/interfaces.
+#[cfg(target_os = "linux")]
+fn read_network_config() -> Option<String> {
+    std::fs::read_to_string("/etc/network/interfaces").ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_network_config() -> Option<String> {
+    None
+}
+
+/// Parses how the IPv4 address of a network interface is configured in
+/// an interfaces configuration, from its `iface <name> inet <method>` stanza.
+fn parse_configured_assignment(config: &str, interface: &str) -> Option<Assignment> {
+    config.lines().find_map(|line| {
+        let words = line.split('#').next().unwrap_or_default().split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            ["iface", name, "inet", "static", ..] if *name == interface => Some(Assignment::Static),
+            ["iface", name, "inet", "dhcp", ..] if *name == interface => Some(Assignment::Dhcp),
+            _ => None,
+        }
+    })
+}
+
+/// A flag of a network interface.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceFlag {
+    /// The interface is administratively up.
+    Up,
+
+    /// The interface supports broadcast.
+    Broadcast,
+
+    /// The interface supports multicast.
+    Multicast,
+
+    /// The interface is a point-to-point link, such as a VPN tunnel.
+    PointToPoint,
+
+    /// The interface is a loopback one.
+    Loopback,
+}
+
+/// Reads the flags of each network interface, keyed by interface name.
+#[cfg(unix)]
+fn read_interface_flags() -> BTreeMap<String, Vec<InterfaceFlag>> {
+    const FLAGS: &[(libc::c_int, InterfaceFlag)] = &[
+        (libc::IFF_UP, InterfaceFlag::Up),
+        (libc::IFF_BROADCAST, InterfaceFlag::Broadcast),
+        (libc::IFF_MULTICAST, InterfaceFlag::Multicast),
+        (libc::IFF_POINTOPOINT, InterfaceFlag::PointToPoint),
+        (libc::IFF_LOOPBACK, InterfaceFlag::Loopback),
+    ];
+
+    let mut flags = BTreeMap::new();
+
+    let mut addresses: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: getifaddrs only writes the address of the list it allocates to
+    // `addresses`, which is freed below with freeifaddrs.
+    if unsafe { libc::getifaddrs(&mut addresses) } != 0 {
+        return flags;
+    }
+
+    // Each address of an interface holds the interface's flags, so the
+    // first one listed is enough
+    let mut cursor = addresses;
+    // SAFETY: each node is either null, ending the list, or points to a node
+    // of the list, which is valid until it is freed.
+    while let Some(address) = unsafe { cursor.as_ref() } {
+        // SAFETY: the interface name is a nul-terminated string owned by the node.
+        let name = unsafe { std::ffi::CStr::from_ptr(address.ifa_name) }.to_string_lossy().into_owned();
+        flags.entry(name).or_insert_with(|| {
+            FLAGS
+                .iter()
+                .filter(|(bit, _)| address.ifa_flags & *bit as libc::c_uint != 0)
+                .map(|(_, flag)| *flag)
+                .collect()
+        });
+
+        cursor = address.ifa_next;
+    }
+
+    // SAFETY: the list was allocated by getifaddrs, and no reference into it
+    // outlives this call, as the names are copied.
+    unsafe { libc::freeifaddrs(addresses) };
+
+    flags
+}
+
+#[cfg(not(unix))]
+fn read_interface_flags() -> BTreeMap<String, Vec<InterfaceFlag>> {
+    BTreeMap::new()
+}
+
+/// The names of the network interfaces carrying the default IPv4 and IPv6 routes.
+#[derive(Default)]
+struct DefaultRoutes {
+    ipv4: Option<String>,
+    ipv6: Option<String>,
+}
+
+/// Reads the network interfaces carrying the default routes from the
+/// routing table.
+fn read_default_routes() -> DefaultRoutes {
+    default_routes(&read_routes().unwrap_or_default())
+}
+
+/// Picks the network interfaces carrying the default routes among the
+/// routes, the route with the lowest metric winning when there are several.
+fn default_routes(routes: &[Route]) -> DefaultRoutes {
+    let default_route = |is_family: fn(&Route) -> bool| {
+        routes
+            .iter()
+            .filter(|route| route.is_default() && is_family(route))
+            .min_by_key(|route| route.metric)
+            .map(|route| route.interface.clone())
+    };
+
+    DefaultRoutes {
+        ipv4: default_route(Route::is_ipv4),
+        ipv6: default_route(|route| !route.is_ipv4()),
+    }
+}
+
+/// Lists the routes of the system's routing table, of both families.
+///
+/// Routes which are down, or which reject the packets they match, are
+/// skipped.
+///
+/// # Returns
+///
+/// The routes, the IPv4 ones first, in the order the system lists them.
+///
+/// # Errors
+///
+/// If the routing table cannot be read, or if the platform is not supported.
+///
+/// # Examples
+///
+/// ```
+/// let routes = network::routes().await.unwrap();
+/// println!("routes: {:?}", routes);
+/// ```
+pub async fn routes() -> Result<Vec<Route>> {
+    spawn_blocking(read_routes).await?
+}
+
+/// A route of the system's routing table.
+#[derive(Deserialize, Serialize)]
+pub struct Route {
+    /// The network the route leads to, in CIDR notation, such as
+    /// `0.0.0.0/0` for the default IPv4 route.
+    pub destination: String,
+
+    /// The gateway the packets are forwarded to, or None when the
+    /// destination is reachable directly on the link.
+    pub gateway: Option<IpAddr>,
+
+    /// The name of the network interface the packets are sent through.
+    pub interface: String,
+
+    /// The metric of the route: the lowest one wins among routes to the
+    /// same destination. Not reported on macOS, where it is always 0.
+    pub metric: u32,
+}
+
+impl Route {
+    /// Returns whether the route is a default route, of either family.
+    fn is_default(&self) -> bool {
+        matches!(self.destination.as_str(), "0.0.0.0/0" | "::/0")
+    }
+
+    /// Returns whether the route's destination is an IPv4 network.
+    fn is_ipv4(&self) -> bool {
+        !self.destination.contains(':')
+    }
+}
+
+/// The routes of the system's routing table, displayed one per line with
+/// their columns aligned.
+#[derive(Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Routes(pub Vec<Route>);
+
+impl Display for Routes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+        let gateways = self
+            .0
+            .iter()
+            .map(|route| match route.gateway {
+                Some(gateway) => format!("via {}", gateway),
+                None => "on-link".to_string(),
+            })
+            .collect::<Vec<String>>();
+
+        let destination_width = self.0.iter().map(|route| route.destination.width()).max().unwrap_or(0);
+        let gateway_width = gateways.iter().map(|gateway| gateway.width()).max().unwrap_or(0);
+        let interface_width = self.0.iter().map(|route| route.interface.width()).max().unwrap_or(0);
+
+        let lines = self
+            .0
+            .iter()
+            .zip(&gateways)
+            .map(|(route, gateway)| {
+                let gateway = pad_right(gateway, gateway_width);
+                let gateway = match route.gateway {
+                    Some(_) => gateway.normal(),
+                    None => gateway.color(palette.muted),
+                };
+
+                format!(
+                    "{}  {}  dev {}  metric {}",
+                    pad_right(&route.destination, destination_width).bold(),
+                    gateway,
+                    pad_right(&route.interface, interface_width).color(palette.name),
+                    route.metric
+                )
+            })
+            .collect::<Vec<String>>();
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Reads the routing table from procfs.
+#[cfg(target_os = "linux")]
+fn read_routes() -> Result<Vec<Route>> {
+    let ipv4_routes = std::fs::read_to_string("/proc/net/route")
+        .with_context(|| "reading the ipv4 routing table from /proc/net/route failed")?;
+    // A host without IPv6 has no IPv6 routing table
+    let ipv6_routes = std::fs::read_to_string("/proc/net/ipv6_route").unwrap_or_default();
+
+    Ok(parse_proc_routes(&ipv4_routes, &ipv6_routes))
+}
+
+/// Parses the IPv4 and IPv6 routing tables procfs lists, in
+/// `/proc/net/route` and `/proc/net/ipv6_route`.
+#[cfg(target_os = "linux")]
+fn parse_proc_routes(ipv4_routes: &str, ipv6_routes: &str) -> Vec<Route> {
+    const RTF_UP: u32 = 0x0001;
+    const RTF_REJECT: u32 = 0x0200;
+
+    let hex = |value: &str| u32::from_str_radix(value, 16).ok();
+    let usable = |flags: u32| flags & RTF_UP != 0 && flags & RTF_REJECT == 0;
+
+    // The IPv4 routes are listed as: iface, destination, gateway, flags,
+    // refcnt, use, metric, mask, and so on, below a header line. Addresses
+    // are the hexadecimal form of their bytes, read as a native integer
+    let ipv4_address = |value: &str| hex(value).map(|value| Ipv4Addr::from(value.to_ne_bytes()));
+    let mut routes = ipv4_routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [iface, destination, gateway, flags, _, _, metric, mask, ..] if usable(hex(flags)?) => {
+                    let gateway = ipv4_address(gateway)?;
+                    let prefix_len = prefix_len(&IpAddr::V4(ipv4_address(mask)?));
+
+                    Some(Route {
+                        destination: format!("{}/{}", ipv4_address(destination)?, prefix_len),
+                        gateway: (!gateway.is_unspecified()).then_some(IpAddr::V4(gateway)),
+                        interface: iface.to_string(),
+                        metric: metric.parse().ok()?,
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect::<Vec<Route>>();
+
+    // The IPv6 routes are listed as: destination, destination prefix length,
+    // source, source prefix length, next hop, metric, refcnt, use, flags,
+    // iface, all in hexadecimal
+    let ipv6_address = |value: &str| u128::from_str_radix(value, 16).ok().map(Ipv6Addr::from);
+    routes.extend(ipv6_routes.lines().filter_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [destination, prefix_len, _, _, next_hop, metric, _, _, flags, iface] if usable(hex(flags)?) => {
+                let next_hop = ipv6_address(next_hop)?;
+
+                Some(Route {
+                    destination: format!("{}/{}", ipv6_address(destination)?, hex(prefix_len)?),
+                    gateway: (!next_hop.is_unspecified()).then_some(IpAddr::V6(next_hop)),
+                    interface: iface.to_string(),
+                    metric: hex(metric)?,
+                })
+            }
+            _ => None,
+        }
+    }));
+
+    routes
+}
+
+/// Reads the routing table from the output of `netstat -rn`.
+///
+/// The IPv4 and IPv6 routes are listed in sections, below a header line
+/// naming the columns, such as:
+/// `Destination        Gateway            Flags           Netif Expire`.
+/// Networks are abbreviated to their significant octets, such as `192.168.1`
+/// for `192.168.1.0/24`, and hosts are listed without a prefix length.
+#[cfg(target_os = "macos")]
+fn read_routes() -> Result<Vec<Route>> {
+    let output = std::process::Command::new("netstat")
+        .arg("-rn")
+        .output()
+        .with_context(|| "running netstat -rn failed")?;
+
+    let mut routes = Vec::new();
+    let mut columns: Option<(usize, usize, usize)> = None;
+    let mut ipv6 = false;
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+
+        match fields.first() {
+            Some(&"Internet:") => ipv6 = false,
+            Some(&"Internet6:") => ipv6 = true,
+            Some(&"Destination") => {
+                let column = |name: &str| fields.iter().position(|field| *field == name);
+                columns = column("Gateway").zip(column("Flags")).zip(column("Netif")).map(|((g, fl), n)| (g, fl, n));
+            }
+            Some(destination) => {
+                let Some((gateway, flags, netif)) = columns else { continue };
+                if fields.len() <= netif || fields[flags].contains('R') {
+                    continue;
+                }
+
+                let Some(destination) = parse_netstat_destination(destination, ipv6) else { continue };
+                let gateway = fields[gateway].split('%').next().and_then(|gateway| gateway.parse::<IpAddr>().ok());
+
+                routes.push(Route {
+                    destination,
+                    gateway: gateway.filter(|gateway| !gateway.is_unspecified()),
+                    interface: fields[netif].to_string(),
+                    metric: 0,
+                });
+            }
+            None => {}
+        }
+    }
+
+    Ok(routes)
+}
+
+/// Parses a destination of `netstat -rn` to CIDR notation.
+#[cfg(target_os = "macos")]
+fn parse_netstat_destination(destination: &str, ipv6: bool) -> Option<String> {
+    if destination == "default" {
+        return Some(if ipv6 { "::/0" } else { "0.0.0.0/0" }.to_string());
+    }
+
+    // Link-local IPv6 destinations carry their scope, such as fe80::%lo0/64
+    let (address, prefix_len) = match destination.split_once('/') {
+        Some((address, prefix_len)) => (address, Some(prefix_len.parse::<u8>().ok()?)),
+        None => (destination, None),
+    };
+    let address = address.split('%').next()?;
+
+    if ipv6 {
+        let address = address.parse::<Ipv6Addr>().ok()?;
+        return Some(format!("{}/{}", address, prefix_len.unwrap_or(128)));
+    }
+
+    let octets = address.split('.').map(|octet| octet.parse::<u8>().ok()).collect::<Option<Vec<u8>>>()?;
+    if octets.is_empty() || octets.len() > 4 {
+        return None;
+    }
+
+    let mut padded = [0u8; 4];
+    padded[..octets.len()].copy_from_slice(&octets);
+    let prefix_len = prefix_len.unwrap_or(octets.len() as u8 * 8);
+
+    Some(format!("{}/{}", Ipv4Addr::from(padded), prefix_len))
+}
+
+/// Reads the IPv4 routing table from the output of `route print -4`.
+///
+/// The interfaces are listed by their IPv4 address, which is resolved to
+/// the name of the interface holding it. The IPv6 routing table is listed
+/// by interface index instead, and is not read.
+#[cfg(target_os = "windows")]
+fn read_routes() -> Result<Vec<Route>> {
+    let output = std::process::Command::new("route")
+        .args(["print", "-4"])
+        .output()
+        .with_context(|| "running route print failed")?;
+
+    let interfaces = get_if_addrs::get_if_addrs()
+        .with_context(|| "listing the network interfaces failed")?
+        .into_iter()
+        .map(|interface| (interface.ip(), interface.name))
+        .collect::<BTreeMap<IpAddr, String>>();
+
+    Ok(parse_route_print(&String::from_utf8_lossy(&output.stdout), |address| {
+        interfaces.get(address).cloned()
+    }))
+}
+
+/// Parses the active routes of the output of `route print -4`.
+///
+/// The headers are localized, so the routes are told apart by their
+/// fields instead: a destination, netmask, gateway, interface address, and
+/// metric, such as `0.0.0.0  0.0.0.0  192.168.1.1  192.168.1.20  25`. A
+/// gateway which is not an address, such as `On-link`, is a route on the
+/// link. The persistent routes are listed without an interface, and thus
+/// skipped.
+#[cfg(any(target_os = "windows", test))]
+fn parse_route_print(output: &str, interface_named: impl Fn(&IpAddr) -> Option<String>) -> Vec<Route> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let [destination, netmask, gateway, interface, metric] = fields.as_slice() else { return None };
+
+            let destination = destination.parse::<Ipv4Addr>().ok()?;
+            let netmask = IpAddr::V4(netmask.parse::<Ipv4Addr>().ok()?);
+            let interface = IpAddr::V4(interface.parse::<Ipv4Addr>().ok()?);
+
+            Some(Route {
+                destination: format!("{}/{}", destination, prefix_len(&netmask)),
+                gateway: gateway.parse::<IpAddr>().ok(),
+                interface: interface_named(&interface).unwrap_or_else(|| interface.to_string()),
+                metric: metric.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_routes() -> Result<Vec<Route>> {
+    Err(error::categorized(
+        ErrorCode::UnsupportedPlatform,
+        "reading the routing table is not supported on this platform",
+    ))
+}
+
+/// Lists the local IP addresses of the system, across all its network interfaces.
+///
+/// Unlike `local_ip_address::local_ip`, which returns a single address on
+/// multi-homed hosts, this enumerates the addresses of every interface.
+///
+/// # Returns
+///
+/// The local IP addresses, in the order the interfaces are listed by the
+/// system, loopback addresses excluded.
+///
+/// # Errors
+///
+/// If the network interfaces cannot be listed.
+///
+/// # Examples
+///
+/// ```
+/// let local_ips = network::local_ips().await.unwrap();
+/// println!("local ips: {:?}", local_ips);
+/// ```
+pub async fn local_ips() -> Result<Vec<IpAddr>> {
+    Ok(interfaces(false)
+        .await?
+        .iter()
+        .map(|interface| interface.ip)
+        .filter(|ip| !ip.is_loopback())
+        .collect())
+}
+
+/// A network interface.
+#[derive(Deserialize, Serialize)]
+pub struct Interface {
+    /// The name of the network interface.
+    name: String,
+
+    /// The kind of the network interface, as guessed from its name.
+    kind: InterfaceKind,
+
+    /// The IP address of the network interface.
+    ip: IpAddr,
+
+    /// The netmask of the network interface's IP address.
+    netmask: IpAddr,
+
+    /// The length of the network interface's IP address prefix,
+    /// as derived from the netmask.
+    prefix_len: u8,
+
+    /// How the network interface's IP address was assigned, as far as it
+    /// can be told. Only detected for detailed listings.
+    #[serde(default)]
+    assignment: Assignment,
+
+    /// The address of the network the interface's IPv4 address belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    network: Option<Ipv4Addr>,
+
+    /// The broadcast address of the network the interface's IPv4 address belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    broadcast: Option<Ipv4Addr>,
+
+    /// The MAC address of the network interface, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+
+    /// The maximum transmission unit of the network interface, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mtu: Option<u32>,
+
+    /// The flags of the network interface, as reported by the platform.
+    /// Only reported on Unix systems.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<Vec<InterfaceFlag>>,
+
+    /// The packet and error counters of the network interface, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counters: Option<InterfaceCounters>,
+
+    /// Whether the address is the one the default route of its family goes
+    /// through. Only detected for detailed listings, on Linux, macOS and Windows.
+    #[serde(default)]
+    is_default_route: bool,
+}
+
+/// The packet and error counters of a network interface.
+///
+/// The counters are cumulative since boot, or since the interface was
+/// created: a nonzero error count only signals a faulty cable or NIC when
+/// it keeps rising between two readings.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct InterfaceCounters {
+    /// The number of packets received by the network interface.
+    packets_received: u64,
+
+    /// The number of packets transmitted by the network interface.
+    packets_transmitted: u64,
+
+    /// The number of errors which occurred while receiving packets.
+    errors_received: u64,
+
+    /// The number of errors which occurred while transmitting packets.
+    errors_transmitted: u64,
+}
+
+impl Display for InterfaceCounters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+        let errors = |count: u64| {
+            let color = if count > 0 { palette.warning } else { palette.good };
+            format!("{} err", human_readable_count(count, CountStyle::Abbreviated)).color(color)
+        };
+
+        write!(
+            f,
+            "rx {} pkts, {}  tx {} pkts, {}",
+            human_readable_count(self.packets_received, CountStyle::Abbreviated),
+            errors(self.errors_received),
+            human_readable_count(self.packets_transmitted, CountStyle::Abbreviated),
+            errors(self.errors_transmitted)
+        )
+    }
+}
+
+impl Interface {
+    /// Returns the MAC address of the network interface, if it has one.
+    pub fn mac(&self) -> Option<&str> {
+        self.mac.as_deref()
+    }
+
+    /// Returns the IP address of the network interface in CIDR notation,
+    /// such as `192.168.1.20/24`.
+    pub fn cidr(&self) -> String {
+        format!("{}/{}", self.ip, self.prefix_len)
+    }
+}
+
+impl Display for Interface {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\t{}", self.name, self.cidr())
+    }
+}
+
+/// The kind of a network interface.
+///
+/// The classification is best-effort: it relies on the naming conventions
+/// of the common operating systems and tools, which interfaces are free to
+/// not follow.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceKind {
+    /// The loopback interface, such as `lo` or `lo0`.
+    Loopback,
+
+    /// A wired interface, such as `eth0`, `enp3s0` or `en0`.
+    Ethernet,
+
+    /// A wireless interface, such as `wlan0` or `wlp2s0`.
+    Wifi,
+
+    /// A software interface, such as a bridge, container, or VPN tunnel.
+    Virtual,
+
+    /// Any other interface.
+    Other,
+}
+
+impl InterfaceKind {
+    /// The name prefixes of virtual interfaces: container and virtual machine
+    /// bridges and links, and VPN tunnels.
+    const VIRTUAL_PREFIXES: &'static [&'static str] = &[
+        "docker", "br-", "veth", "virbr", "vmnet", "vboxnet", "bridge", "cni", "flannel", "cali",
+        "tun", "tap", "utun", "wg", "tailscale", "zt",
+    ];
+
+    /// Classifies a network interface from its name, and whether it is flagged as a loopback one.
+    pub fn classify(name: &str, is_loopback: bool) -> InterfaceKind {
+        let is_loopback_name = name
+            .strip_prefix("lo")
+            .map(|suffix| suffix.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or(false);
+
+        match name {
+            _ if is_loopback || is_loopback_name => InterfaceKind::Loopback,
+            _ if Self::VIRTUAL_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) => InterfaceKind::Virtual,
+            _ if name.starts_with("wl") => InterfaceKind::Wifi,
+            _ if name.starts_with("en") || name.starts_with("eth") => InterfaceKind::Ethernet,
+            _ => InterfaceKind::Other,
+        }
+    }
+}
+
+/// The network interfaces of the system.
+///
+/// They are serialized as an object keyed by interface name, holding each
+/// interface's kind and addresses in CIDR notation split by family, along
+/// with its MAC address, MTU, flags, counters, and whether it carries a
+/// default route. The details of each address, such as its netmask, prefix
+/// length, network and broadcast addresses, and how it was assigned, are
+/// listed under `addresses`, holding the same fields as the flat output.
+/// When flat, they are serialized as an array with one entry
+/// per address instead. The counters are only displayed in text when requested.
+pub struct Interfaces {
+    pub interfaces: Vec<Interface>,
+    pub flat: bool,
+    pub counters: bool,
+}
+
+/// The addresses and link details of a network interface, grouped under its name.
+#[derive(Default, Serialize)]
+struct InterfaceGroup<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<InterfaceKind>,
+
+    ipv4: Vec<String>,
+    ipv6: Vec<String>,
+
+    addresses: Vec<GroupedAddress>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mtu: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<&'a [InterfaceFlag]>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    counters: Option<InterfaceCounters>,
+
+    is_default_route: bool,
+}
+
+/// The details of an address of a network interface, as serialized grouped
+/// under the interface's name.
+#[derive(Deserialize, Serialize)]
+struct GroupedAddress {
+    ip: IpAddr,
+    netmask: IpAddr,
+    prefix_len: u8,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    network: Option<Ipv4Addr>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    broadcast: Option<Ipv4Addr>,
+
+    #[serde(default)]
+    assignment: Assignment,
+}
+
+impl Display for Interfaces {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The names are padded rather than tab-separated from the addresses,
+        // so that the addresses line up regardless of the names' lengths
+        let name_width = self.interfaces.iter().map(|interface| interface.name.width()).max().unwrap_or(0);
+
+        write!(
+            f,
+            "{}",
+            self.interfaces
+                .iter()
+                .map(|interface| {
+                    let line = format!("{}  {}", pad_right(&interface.name, name_width), interface.cidr());
+                    match &interface.counters {
+                        Some(counters) if self.counters => format!("{}  {}", line, counters),
+                        _ => line,
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        )
+    }
+}
+
+impl Serialize for Interfaces {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.flat {
+            return self.interfaces.serialize(serializer);
+        }
+
+        let mut groups: BTreeMap<&str, InterfaceGroup> = BTreeMap::new();
+        for interface in &self.interfaces {
+            let group = groups.entry(&interface.name).or_default();
+
+            match interface.ip {
+                IpAddr::V4(_) => group.ipv4.push(interface.cidr()),
+                IpAddr::V6(_) => group.ipv6.push(interface.cidr()),
+            }
+            group.addresses.push(GroupedAddress {
+                ip: interface.ip,
+                netmask: interface.netmask,
+                prefix_len: interface.prefix_len,
+                network: interface.network,
+                broadcast: interface.broadcast,
+                assignment: interface.assignment,
+            });
+
+            group.kind = group.kind.or(Some(interface.kind));
+            group.mac = group.mac.or(interface.mac.as_deref());
+            group.mtu = group.mtu.or(interface.mtu);
+            group.flags = group.flags.or(interface.flags.as_deref());
+            group.counters = group.counters.or(interface.counters);
+            group.is_default_route |= interface.is_default_route;
+        }
+
+        groups.serialize(serializer)
+    }
+}
+
+/// The network interfaces, as serialized flat or grouped under their names.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedInterfaces {
+    Flat(Vec<Interface>),
+    Grouped(BTreeMap<String, SerializedInterfaceGroup>),
+}
+
+/// The addresses and link details of a network interface, as serialized
+/// grouped under its name.
+#[derive(Deserialize)]
+struct SerializedInterfaceGroup {
+    kind: Option<InterfaceKind>,
+    ipv4: Vec<String>,
+    ipv6: Vec<String>,
+
+    #[serde(default)]
+    addresses: Vec<GroupedAddress>,
+
+    mac: Option<String>,
+    mtu: Option<u32>,
+    flags: Option<Vec<InterfaceFlag>>,
+    counters: Option<InterfaceCounters>,
+
+    #[serde(default)]
+    is_default_route: bool,
+}
+
+impl<'de> Deserialize<'de> for Interfaces {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let groups = match SerializedInterfaces::deserialize(deserializer)? {
+            SerializedInterfaces::Flat(interfaces) => return Ok(Interfaces { interfaces, flat: true, counters: false }),
+            SerializedInterfaces::Grouped(groups) => groups,
+        };
+
+        // The grouped form only holds the addresses in CIDR notation, from
+        // which the netmask, network and broadcast addresses are derived back
+        let mut interfaces = Vec::new();
+        for (name, group) in groups {
+            for (index, cidr) in group.ipv4.iter().chain(&group.ipv6).enumerate() {
+                let (ip, prefix_len) = parse_cidr(cidr).map_err(serde::de::Error::custom)?;
+                let (network, broadcast) = match ip {
+                    IpAddr::V4(ip) => {
+                        let (network, broadcast) = ipv4_network(ip, prefix_len);
+                        (Some(network), Some(broadcast))
+                    }
+                    IpAddr::V6(_) => (None, None),
+                };
+
+                interfaces.push(Interface {
+                    name: name.clone(),
+                    kind: group.kind.unwrap_or_else(|| InterfaceKind::classify(&name, false)),
+                    ip,
+                    netmask: netmask(&ip, prefix_len),
+                    prefix_len,
+                    assignment: group
+                        .addresses
+                        .iter()
+                        .find(|address| address.ip == ip)
+                        .map(|address| address.assignment)
+                        .unwrap_or_default(),
+                    network,
+                    broadcast,
+                    mac: group.mac.clone(),
+                    mtu: group.mtu,
+                    flags: group.flags.clone(),
+                    counters: group.counters,
+                    is_default_route: group.is_default_route && index == 0,
+                });
+            }
+        }
+
+        Ok(Interfaces { interfaces, flat: false, counters: false })
+    }
+}
+
+/// Computes the length of the prefix described by a netmask,
+/// such as 24 for `255.255.255.0`.
+pub fn prefix_len(netmask: &IpAddr) -> u8 {
+    let ones = match netmask {
+        IpAddr::V4(netmask) => u32::from(*netmask).count_ones(),
+        IpAddr::V6(netmask) => u128::from(*netmask).count_ones(),
+    };
+
+    ones as u8
+}
+
+/// Computes the network and broadcast addresses of the network an IPv4
+/// address belongs to, given the length of its prefix.
+///
+/// For instance, `192.168.1.20/24` belongs to the `192.168.1.0` network,
+/// whose broadcast address is `192.168.1.255`.
+pub fn ipv4_network(ip: Ipv4Addr, prefix_len: u8) -> (Ipv4Addr, Ipv4Addr) {
+    let mask = u32::MAX.checked_shl(32 - prefix_len.min(32) as u32).unwrap_or(0);
+    let ip = u32::from(ip);
+
+    (Ipv4Addr::from(ip & mask), Ipv4Addr::from(ip | !mask))
+}
+
+/// Computes the netmask of an IP address' family for a prefix length,
+/// such as `255.255.255.0` for an IPv4 address and 24.
+fn netmask(ip: &IpAddr, prefix_len: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(u32::MAX.checked_shl(32 - prefix_len.min(32) as u32).unwrap_or(0))),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(u128::MAX.checked_shl(128 - prefix_len.min(128) as u32).unwrap_or(0))),
+    }
+}
+
+/// Parses an IP address in CIDR notation, such as `192.168.1.20/24`, into
+/// the address and the length of its prefix.
+fn parse_cidr(cidr: &str) -> Result<(IpAddr, u8)> {
+    let (ip, prefix_len) = cidr
+        .split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("{} is not in CIDR notation", cidr))?;
+
+    Ok((ip.parse()?, prefix_len.parse()?))
+}
+
+/// Reads the maximum transmission unit of a network interface from sysfs.
+#[cfg(target_os = "linux")]
+fn read_mtu(interface: &str) -> Option<u32> {
+    std::fs::read_to_string(format!("/sys/class/net/{}/mtu", interface))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_mtu(_interface: &str) -> Option<u32> {
+    None
+}
+
+/// Lists the entries of the system's ARP/neighbor cache.
+///
+/// # Returns
+///
+/// A vector holding the neighbors, in the order the system reports them.
+/// Neighbors whose hardware address is not resolved yet have no MAC address,
+/// and an incomplete state.
+///
+/// # Errors
+///
+/// If the neighbor cache cannot be read, or if the platform is not supported.
+///
+/// # Examples
+///
+/// ```
+/// let neighbors = network::neighbors().await.unwrap();
+/// println!("neighbors: {:?}", neighbors);
+/// ```
+pub async fn neighbors() -> Result<Vec<Neighbor>> {
+    spawn_blocking(read_neighbors).await?
+}
+
+/// An entry of the ARP/neighbor cache.
+#[derive(Deserialize, Serialize)]
+pub struct Neighbor {
+    /// The IP address of the neighbor.
+    pub ip: IpAddr,
+
+    /// The MAC address of the neighbor, if resolved.
+    pub mac: Option<String>,
+
+    /// The name of the network interface the neighbor is reachable through.
+    pub interface: String,
+
+    /// The state of the entry: incomplete, complete or permanent.
+    pub state: String,
+}
+
+impl Display for Neighbor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}",
+            self.ip,
+            self.mac.as_deref().unwrap_or("-"),
+            self.interface,
+            self.state
+        )
+    }
+}
+
+/// The path of the file exposing the ARP cache on Linux
+#[cfg(target_os = "linux")]
+const PROC_NET_ARP_PATH: &str = "/proc/net/arp";
+
+/// Reads the ARP cache from /proc/net/arp.
+#[cfg(target_os = "linux")]
+fn read_neighbors() -> Result<Vec<Neighbor>> {
+    let content = std::fs::read_to_string(PROC_NET_ARP_PATH)
+        .with_context(|| format!("reading the arp cache from {} failed", PROC_NET_ARP_PATH))?;
+
+    parse_proc_net_arp(&content)
+}
+
+/// Parses the ARP cache from the content of /proc/net/arp.
+///
+/// Each line past the header holds the IP address, hardware type, flags,
+/// hardware address, mask and device of an entry.
+#[cfg(target_os = "linux")]
+fn parse_proc_net_arp(content: &str) -> Result<Vec<Neighbor>> {
+    // The entry is complete, and holds a resolved hardware address
+    const ATF_COM: u32 = 0x02;
+    // The entry is permanent, and never expires
+    const ATF_PERM: u32 = 0x04;
+
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            (fields.len() >= 6).then_some(fields)
+        })
+        .map(|fields| {
+            let ip = fields[0]
+                .parse::<IpAddr>()
+                .with_context(|| format!("parsing arp entry ip address {} failed", fields[0]))?;
+            let flags = u32::from_str_radix(fields[2].trim_start_matches("0x"), 16)
+                .with_context(|| format!("parsing arp entry flags {} failed", fields[2]))?;
+
+            let state = match flags {
+                _ if flags & ATF_PERM != 0 => "permanent",
+                _ if flags & ATF_COM != 0 => "complete",
+                _ => "incomplete",
+            };
+
+            Ok(Neighbor {
+                ip,
+                mac: (flags & ATF_COM != 0).then(|| fields[3].to_string()),
+                interface: fields[5].to_string(),
+                state: state.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads the ARP cache from the output of `arp -an`.
+#[cfg(target_os = "macos")]
+fn read_neighbors() -> Result<Vec<Neighbor>> {
+    let output = std::process::Command::new("arp")
+        .arg("-an")
+        .output()
+        .with_context(|| "running arp -an failed")?;
+
+    parse_arp_an(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parses the ARP cache from the output of `arp -an`.
+///
+/// Each line is formatted as `? (192.168.1.1) at a4:83:e7:0:11:22 on en0 ifscope [ethernet]`,
+/// where the hardware address is `(incomplete)` for unresolved entries, and the
+/// `permanent` keyword marks static entries.
+#[cfg(any(target_os = "macos", test))]
+fn parse_arp_an(output: &str) -> Result<Vec<Neighbor>> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            (fields.len() >= 6 && fields[2] == "at" && fields[4] == "on").then_some(fields)
+        })
+        .map(|fields| {
+            let ip = fields[1]
+                .trim_matches(|c| c == '(' || c == ')')
+                .parse::<IpAddr>()
+                .with_context(|| format!("parsing arp entry ip address {} failed", fields[1]))?;
+            let mac = (fields[3] != "(incomplete)").then(|| fields[3].to_string());
+
+            let state = match mac {
+                None => "incomplete",
+                Some(_) if fields.contains(&"permanent") => "permanent",
+                Some(_) => "complete",
+            };
+
+            Ok(Neighbor {
+                ip,
+                mac,
+                interface: fields[5].to_string(),
+                state: state.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Reads the IPv4 and IPv6 neighbor cache with `GetIpNetTable2`.
+///
+/// The interfaces are named by their GUID, as the interfaces listing names
+/// them. Entries that are unreachable or still being resolved are reported
+/// as incomplete.
+#[cfg(target_os = "windows")]
+fn read_neighbors() -> Result<Vec<Neighbor>> {
+    const AF_UNSPEC: u16 = 0;
+    const AF_INET: u16 = 2;
+    const AF_INET6: u16 = 23;
+    const NL_NEIGHBOR_STATE_UNREACHABLE: i32 = 0;
+    const NL_NEIGHBOR_STATE_INCOMPLETE: i32 = 1;
+    const NL_NEIGHBOR_STATE_PERMANENT: i32 = 6;
+
+    /// An entry of the neighbor table, laid out as a MIB_IPNET_ROW2.
+    #[repr(C)]
+    struct MibIpnetRow2 {
+        // A SOCKADDR_INET: the address family, followed by the fields of
+        // either a SOCKADDR_IN or a SOCKADDR_IN6
+        address: [u8; 28],
+        interface_index: u32,
+        interface_luid: u64,
+        physical_address: [u8; 32],
+        physical_address_length: u32,
+        state: i32,
+        _flags: u8,
+        _reachability_time: u32,
+    }
+
+    /// The neighbor table, laid out as a MIB_IPNET_TABLE2: its rows follow
+    /// their count.
+    #[repr(C)]
+    struct MibIpnetTable2 {
+        num_entries: u32,
+        table: [MibIpnetRow2; 1],
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Guid {
+        data1: u32,
+        data2: u16,
+        data3: u16,
+        data4: [u8; 8],
+    }
+
+    #[link(name = "iphlpapi")]
+    extern "system" {
+        fn GetIpNetTable2(family: u16, table: *mut *mut MibIpnetTable2) -> u32;
+
+        fn FreeMibTable(memory: *const std::ffi::c_void);
+
+        fn ConvertInterfaceLuidToGuid(interface_luid: *const u64, interface_guid: *mut Guid) -> u32;
+    }
+
+    let interface_named = |row: &MibIpnetRow2| {
+        let mut guid = Guid::default();
+
+        // SAFETY: the LUID and the GUID both point to live values, laid out
+        // as the API expects.
+        match unsafe { ConvertInterfaceLuidToGuid(&row.interface_luid, &mut guid) } {
+            0 => format!(
+                "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{}}}",
+                guid.data1,
+                guid.data2,
+                guid.data3,
+                guid.data4[0],
+                guid.data4[1],
+                guid.data4[2..].iter().map(|byte| format!("{:02X}", byte)).collect::<String>()
+            ),
+            _ => row.interface_index.to_string(),
+        }
+    };
+
+    let mut table: *mut MibIpnetTable2 = std::ptr::null_mut();
+
+    // SAFETY: GetIpNetTable2 allocates the table, and only writes its
+    // address to `table`.
+    let status = unsafe { GetIpNetTable2(AF_UNSPEC, &mut table) };
+    if status != 0 {
+        return Err(std::io::Error::from_raw_os_error(status as i32)).with_context(|| "reading the neighbor table failed");
+    }
+
+    // SAFETY: the table is non-null, as the call succeeded, and holds
+    // `num_entries` rows. It is only freed once they are no longer borrowed.
+    let rows = unsafe { std::slice::from_raw_parts((*table).table.as_ptr(), (*table).num_entries as usize) };
+
+    let neighbors = rows
+        .iter()
+        .filter_map(|row| {
+            let ip = match u16::from_ne_bytes([row.address[0], row.address[1]]) {
+                AF_INET => IpAddr::from(<[u8; 4]>::try_from(&row.address[4..8]).ok()?),
+                AF_INET6 => IpAddr::from(<[u8; 16]>::try_from(&row.address[8..24]).ok()?),
+                _ => return None,
+            };
+
+            let resolved = !matches!(row.state, NL_NEIGHBOR_STATE_UNREACHABLE | NL_NEIGHBOR_STATE_INCOMPLETE);
+            let mac_len = (row.physical_address_length as usize).min(row.physical_address.len());
+            let mac = (resolved && mac_len > 0).then(|| {
+                row.physical_address[..mac_len]
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<Vec<String>>()
+                    .join(":")
+            });
+
+            let state = match row.state {
+                NL_NEIGHBOR_STATE_PERMANENT => "permanent",
+                _ if resolved => "complete",
+                _ => "incomplete",
+            };
+
+            Some(Neighbor {
+                ip,
+                mac,
+                interface: interface_named(row),
+                state: state.to_string(),
+            })
+        })
+        .collect();
+
+    // SAFETY: the table was allocated by GetIpNetTable2, and its rows are no
+    // longer borrowed.
+    unsafe { FreeMibTable(table as *const std::ffi::c_void) };
+
+    Ok(neighbors)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_neighbors() -> Result<Vec<Neighbor>> {
+    Err(error::categorized(
+        ErrorCode::UnsupportedPlatform,
+        "listing the arp cache is not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_query_id_varies_between_queries() {
+        let ids = (0..16).map(|_| random_query_id()).collect::<BTreeSet<u16>>();
+
+        assert!(ids.len() > 1);
+    }
+
+    #[test]
+    fn ip_round_trips_through_its_serialized_form() {
+        let ip = Ip { address: "192.168.1.10".parse().unwrap(), category: IpCategory::Local, via: None };
+
+        let serialized = serde_json::to_value(&ip).unwrap();
+        let deserialized: Ip = serde_json::from_value(serialized.clone()).unwrap();
+
+        assert_eq!(serialized["ip"], "192.168.1.10");
+        assert_eq!(serialized["category"], "local");
+        assert_eq!(deserialized.address, ip.address);
+        assert_eq!(deserialized.category, IpCategory::Local);
+        assert_eq!(deserialized.via, None);
+    }
+
+    #[test]
+    fn ip_category_deserializes_its_former_capitalized_names() {
+        for (name, category) in [("\"Public\"", IpCategory::Public), ("\"Local\"", IpCategory::Local)] {
+            assert_eq!(serde_json::from_str::<IpCategory>(name).unwrap(), category);
+        }
+
+        for category in [IpCategory::Public, IpCategory::Local, IpCategory::Any] {
+            let serialized = serde_json::to_string(&category).unwrap();
+            assert_eq!(serialized, format!("\"{}\"", category));
+            assert_eq!(serde_json::from_str::<IpCategory>(&serialized).unwrap(), category);
+        }
+    }
+
+    #[test]
+    fn parse_route_print_reads_the_active_routes() {
+        let output = "\
+===========================================================================
+IPv4 Route Table
+===========================================================================
+Active Routes:
+Network Destination        Netmask          Gateway       Interface  Metric
+          0.0.0.0          0.0.0.0      192.168.1.1    192.168.1.20     25
+        127.0.0.0        255.0.0.0         On-link         127.0.0.1    331
+      192.168.1.0    255.255.255.0         On-link      192.168.1.20    281
+===========================================================================
+Persistent Routes:
+  Network Address          Netmask  Gateway Address  Metric
+          0.0.0.0          0.0.0.0      192.168.1.1  Default
+===========================================================================
+";
+        let interface_named = |address: &IpAddr| (*address == IpAddr::from([192, 168, 1, 20])).then(|| "Ethernet".to_string());
+
+        let routes = parse_route_print(output, interface_named)
+            .into_iter()
+            .map(|route| (route.destination, route.gateway, route.interface, route.metric))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            routes,
+            vec![
+                ("0.0.0.0/0".to_string(), Some(IpAddr::from([192, 168, 1, 1])), "Ethernet".to_string(), 25),
+                ("127.0.0.0/8".to_string(), None, "127.0.0.1".to_string(), 331),
+                ("192.168.1.0/24".to_string(), None, "Ethernet".to_string(), 281),
+            ]
+        );
+    }
+
+    #[test]
+    fn ip_serializes_its_resolver_only_when_set() {
+        let public = Ip { address: "203.0.113.7".parse().unwrap(), category: IpCategory::Public, via: Some("opendns".to_string()) };
+        let local = Ip { address: "192.168.1.10".parse().unwrap(), category: IpCategory::Local, via: None };
+
+        let public = serde_json::to_value(&public).unwrap();
+        let local = serde_json::to_value(&local).unwrap();
+
+        assert_eq!(public["via"], "opendns");
+        assert!(local.get("via").is_none());
+    }
+
+    #[test]
+    fn ip_scope_of_classifies_addresses() {
+        let scope = |address: &str| IpScope::of(&address.parse().unwrap());
+
+        assert_eq!(scope("192.168.0.1"), IpScope::Private);
+        assert_eq!(scope("8.8.8.8"), IpScope::Global);
+        assert_eq!(scope("127.0.0.1"), IpScope::Loopback);
+        assert_eq!(scope("fe80::1"), IpScope::LinkLocal);
+        assert_eq!(scope("::ffff:192.168.0.1"), IpScope::Private);
+    }
+
+    #[test]
+    fn prefix_len_counts_the_netmask_bits() {
+        assert_eq!(prefix_len(&"255.255.255.0".parse().unwrap()), 24);
+        assert_eq!(prefix_len(&"255.255.240.0".parse().unwrap()), 20);
+        assert_eq!(prefix_len(&"0.0.0.0".parse().unwrap()), 0);
+        assert_eq!(prefix_len(&"ffff:ffff:ffff:ffff::".parse().unwrap()), 64);
+    }
+
+    #[test]
+    fn ipv4_network_computes_the_network_and_broadcast() {
+        let ip = "192.168.1.20".parse().unwrap();
+
+        assert_eq!(ipv4_network(ip, 24), (Ipv4Addr::new(192, 168, 1, 0), Ipv4Addr::new(192, 168, 1, 255)));
+        assert_eq!(ipv4_network(ip, 30), (Ipv4Addr::new(192, 168, 1, 20), Ipv4Addr::new(192, 168, 1, 23)));
+        assert_eq!(ipv4_network(ip, 32), (ip, ip));
+        assert_eq!(ipv4_network(ip, 0), (Ipv4Addr::UNSPECIFIED, Ipv4Addr::BROADCAST));
+    }
+
+    #[test]
+    fn interface_kind_classify_tells_the_kind_from_the_name() {
+        let cases = [
+            ("lo", false, InterfaceKind::Loopback),
+            ("lo0", false, InterfaceKind::Loopback),
+            ("eth0", false, InterfaceKind::Ethernet),
+            ("enp3s0", false, InterfaceKind::Ethernet),
+            ("en0", false, InterfaceKind::Ethernet),
+            ("wlan0", false, InterfaceKind::Wifi),
+            ("wlp2s0", false, InterfaceKind::Wifi),
+            ("docker0", false, InterfaceKind::Virtual),
+            ("veth1a2b3c", false, InterfaceKind::Virtual),
+            ("utun3", false, InterfaceKind::Virtual),
+            ("wg0", false, InterfaceKind::Virtual),
+            ("local", false, InterfaceKind::Other),
+            ("ib0", false, InterfaceKind::Other),
+            ("ib0", true, InterfaceKind::Loopback),
+        ];
+
+        for (name, is_loopback, kind) in cases {
+            assert_eq!(InterfaceKind::classify(name, is_loopback), kind, "{}", name);
+        }
+    }
+
+    #[test]
+    fn ip_category_never_renders_as_a_wildcard() {
+        for category in [IpCategory::Public, IpCategory::Local, IpCategory::Any] {
+            assert!(!category.to_string().contains('*'), "{:?}", category);
+        }
+        assert_eq!(IpCategory::Any.to_string(), "any");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_proc_routes_reads_the_default_gateway() {
+        let ipv4_routes = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+wlan0\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+eth0\t00000000\t0100000A\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0000000A\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0
+eth1\t00000000\t0200000A\t0002\t0\t0\t0\t00000000\t0\t0\t0
+";
+        let ipv6_routes = "\
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003     eth0
+";
+
+        let routes = parse_proc_routes(ipv4_routes, ipv6_routes);
+        let summary = routes
+            .iter()
+            .map(|route| (route.destination.as_str(), route.gateway, route.interface.as_str(), route.metric))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("0.0.0.0/0", Some(IpAddr::from([192, 168, 1, 1])), "wlan0", 600),
+                ("0.0.0.0/0", Some(IpAddr::from([10, 0, 0, 1])), "eth0", 100),
+                ("10.0.0.0/24", None, "eth0", 100),
+                ("::/0", Some("fe80::1".parse().unwrap()), "eth0", 1024),
+            ]
+        );
+
+        let default_routes = default_routes(&routes);
+        assert_eq!(default_routes.ipv4.as_deref(), Some("eth0"));
+        assert_eq!(default_routes.ipv6.as_deref(), Some("eth0"));
+    }
+
+    #[test]
+    fn source_ip_is_the_address_packets_leave_from() {
+        let loopback = source_ip(IpAddr::from([127, 0, 0, 1])).unwrap();
+        assert_eq!(loopback.source_ip, IpAddr::from([127, 0, 0, 1]));
+
+        // A routable destination, reserved for documentation, which is never
+        // sent anything: connecting a udp socket only picks the route. Hosts
+        // without a default route have no source address for it
+        let destination = IpAddr::from([192, 0, 2, 1]);
+        if let Ok(routable) = source_ip(destination) {
+            assert_eq!(routable.destination, destination);
+            assert!(!routable.source_ip.is_unspecified() && !routable.source_ip.is_loopback());
+        }
+    }
+
+    fn interface(name: &str, ip: &str) -> Interface {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "kind": InterfaceKind::classify(name, false),
+            "ip": ip,
+            "netmask": if ip.contains(':') { "ffff:ffff:ffff:ffff::" } else { "255.255.255.0" },
+            "prefix_len": if ip.contains(':') { 64 } else { 24 },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn mark_default_routes_marks_one_address_per_family() {
+        let mut interfaces = vec![
+            interface("eth0", "192.168.1.20"),
+            interface("eth0", "192.168.1.21"),
+            interface("eth0", "fe80::1"),
+            interface("eth0", "2001:db8::20"),
+            interface("wlan0", "10.0.0.5"),
+            interface("wlan0", "2001:db8:1::5"),
+        ];
+        let default_routes = DefaultRoutes {
+            ipv4: Some("eth0".to_string()),
+            ipv6: Some("wlan0".to_string()),
+        };
+
+        mark_default_routes(&mut interfaces, &default_routes);
+
+        let marked = interfaces
+            .iter()
+            .filter(|interface| interface.is_default_route)
+            .map(|interface| (interface.name.as_str(), interface.ip))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            marked,
+            vec![
+                ("eth0", IpAddr::from([192, 168, 1, 20])),
+                ("wlan0", "2001:db8:1::5".parse().unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn mark_default_routes_marks_nothing_without_a_matching_interface() {
+        let mut interfaces = vec![interface("eth0", "192.168.1.20"), interface("eth0", "2001:db8::20")];
+        let default_routes = DefaultRoutes {
+            ipv4: Some("eth1".to_string()),
+            ipv6: None,
+        };
+
+        mark_default_routes(&mut interfaces, &default_routes);
+
+        assert!(interfaces.iter().all(|interface| !interface.is_default_route));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_lease_addresses_reads_the_dhclient_blocks_of_the_interface() {
+        let lease = r#"
+lease {
+  interface "eth0";
+  fixed-address 192.168.1.20;
+  option subnet-mask 255.255.255.0;
+}
+lease {
+  interface "wlan0";
+  fixed-address 10.0.0.5;
+}
+lease {
+  interface "eth0";
+  fixed-address 192.168.1.21;
+}
+"#;
+
+        assert_eq!(
+            parse_lease_addresses(lease, "eth0", false),
+            vec![IpAddr::from([192, 168, 1, 20]), IpAddr::from([192, 168, 1, 21])]
+        );
+        assert_eq!(parse_lease_addresses(lease, "wlan0", false), vec![IpAddr::from([10, 0, 0, 5])]);
+        assert!(parse_lease_addresses(lease, "eth1", false).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_lease_addresses_reads_the_address_of_an_interface_lease() {
+        let lease = "# This is private data. Do not parse.\nADDRESS=192.168.1.20\nNETMASK=255.255.255.0\nROUTER=192.168.1.1\n";
+
+        assert_eq!(parse_lease_addresses(lease, "eth0", true), vec![IpAddr::from([192, 168, 1, 20])]);
+        // A lease not known to be the interface's does not name it
+        assert!(parse_lease_addresses(lease, "eth0", false).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn lease_file_addresses_tells_the_interface_from_the_file_name() {
+        let lease_files = vec![
+            ("internal-1234-eth0.lease".to_string(), "ADDRESS=192.168.1.20\n".to_string()),
+            ("internal-1234-wlan0.lease".to_string(), "ADDRESS=10.0.0.5\n".to_string()),
+        ];
+
+        assert_eq!(lease_file_addresses(&lease_files, "eth0"), vec![IpAddr::from([192, 168, 1, 20])]);
+    }
+
+    #[test]
+    fn parse_configured_assignment_reads_the_inet_method() {
+        let config = "\
+auto lo
+iface lo inet loopback
+
+iface eth0 inet static
+    address 192.168.1.20/24
+iface wlan0 inet dhcp
+# iface eth1 inet static
+";
+
+        assert_eq!(parse_configured_assignment(config, "eth0"), Some(Assignment::Static));
+        assert_eq!(parse_configured_assignment(config, "wlan0"), Some(Assignment::Dhcp));
+        assert_eq!(parse_configured_assignment(config, "eth1"), None);
+        assert_eq!(parse_configured_assignment(config, "lo"), None);
+    }
+
+    #[test]
+    fn interface_assignment_prefers_the_leases() {
+        let assignment = InterfaceAssignment {
+            lease_addresses: vec![IpAddr::from([192, 168, 1, 20])],
+            configured: Some(Assignment::Static),
+        };
+
+        assert_eq!(assignment.assignment(&IpAddr::from([192, 168, 1, 20])), Assignment::Dhcp);
+        assert_eq!(assignment.assignment(&IpAddr::from([192, 168, 1, 21])), Assignment::Static);
+        assert_eq!(assignment.assignment(&"2001:db8::20".parse().unwrap()), Assignment::Unknown);
+        assert_eq!(assignment.assignment(&IpAddr::from([127, 0, 0, 1])), Assignment::Static);
+    }
+
+    #[test]
+    fn interfaces_addresses_line_up() {
+        colored::control::set_override(false);
+
+        let interfaces = Interfaces {
+            interfaces: vec![interface("lo", "127.0.0.1"), interface("wlp0s20f3", "192.168.1.20"), interface("eth0", "fe80::1")],
+            flat: false,
+            counters: false,
+        };
+
+        let rendered = interfaces.to_string();
+        let offsets: Vec<usize> = rendered
+            .lines()
+            .zip(["127.0.0.1/24", "192.168.1.20/24", "fe80::1/64"])
+            .map(|(line, cidr)| line.find(cidr).unwrap())
+            .collect();
+        assert_eq!(offsets, vec!["wlp0s20f3  ".len(); 3], "{}", rendered);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_proc_net_arp_reads_the_entries_and_their_state() {
+        let content = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         a4:83:e7:00:11:22     *        wlan0
+192.168.1.42     0x1         0x0         00:00:00:00:00:00     *        wlan0
+10.0.0.1         0x1         0x6         52:54:00:12:34:56     *        eth0
+";
+
+        let neighbors = parse_proc_net_arp(content).unwrap();
+        let entries: Vec<(String, Option<&str>, &str, &str)> = neighbors
+            .iter()
+            .map(|neighbor| (neighbor.ip.to_string(), neighbor.mac.as_deref(), neighbor.interface.as_str(), neighbor.state.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("192.168.1.1".to_string(), Some("a4:83:e7:00:11:22"), "wlan0", "complete"),
+                ("192.168.1.42".to_string(), None, "wlan0", "incomplete"),
+                ("10.0.0.1".to_string(), Some("52:54:00:12:34:56"), "eth0", "permanent"),
+            ]
+        );
+
+        assert!(parse_proc_net_arp("IP address HW type Flags HW address Mask Device\n").unwrap().is_empty());
+        assert!(parse_proc_net_arp("header\nnot-an-ip 0x1 0x2 a4:83:e7:00:11:22 * wlan0\n").is_err());
+    }
+
+    #[test]
+    fn parse_arp_an_reads_the_entries_and_their_state() {
+        let output = "\
+? (192.168.1.1) at a4:83:e7:0:11:22 on en0 ifscope [ethernet]
+? (192.168.1.42) at (incomplete) on en0 ifscope [ethernet]
+? (192.168.1.255) at ff:ff:ff:ff:ff:ff on en0 ifscope permanent [ethernet]
+";
+
+        let neighbors = parse_arp_an(output).unwrap();
+        let entries: Vec<(String, Option<&str>, &str, &str)> = neighbors
+            .iter()
+            .map(|neighbor| (neighbor.ip.to_string(), neighbor.mac.as_deref(), neighbor.interface.as_str(), neighbor.state.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("192.168.1.1".to_string(), Some("a4:83:e7:0:11:22"), "en0", "complete"),
+                ("192.168.1.42".to_string(), None, "en0", "incomplete"),
+                ("192.168.1.255".to_string(), Some("ff:ff:ff:ff:ff:ff"), "en0", "permanent"),
+            ]
+        );
+
+        assert!(parse_arp_an("arp: no entries\n").unwrap().is_empty());
     }
 }
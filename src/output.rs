@@ -1,7 +1,11 @@
 use std::fmt::Display;
+use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Local, SecondsFormat};
 use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{Map, Value};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Named is an enum that represents a named value.
 ///
@@ -12,6 +16,8 @@ use serde::ser::{Serialize, SerializeMap, Serializer};
 /// that returns a single value, but also provides a name for that
 /// value. So that the output can be serialized to JSON in a meaningful
 /// way, for example.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Named {
     Hostname(String),
     Username(String),
@@ -79,3 +85,663 @@ where
         NamedKind::Architecture => Ok(Named::Architecture(value)),
     }
 }
+
+/// The version of the shape of the serialized results.
+///
+/// It is bumped whenever a result's serialized shape changes in a way that
+/// breaks existing consumers, such as a field being removed, renamed, or
+/// changing type. Adding fields does not bump it.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Envelope wraps a result along with metadata describing it,
+/// without polluting the result's own serialized shape.
+#[derive(serde::Serialize)]
+pub struct Envelope<'a, T: Serialize> {
+    pub schema_version: u32,
+    pub data: &'a T,
+}
+
+impl<'a, T: Serialize> Envelope<'a, T> {
+    pub fn new(data: &'a T) -> Self {
+        Envelope {
+            schema_version: SCHEMA_VERSION,
+            data,
+        }
+    }
+}
+
+/// OutputSpec is an output configuration expressed as a single string, such
+/// as `json;pretty=false;fields=brand,frequency`.
+///
+/// The spec starts with the name of the output format, followed by
+/// semicolon-separated `key=value` options:
+///   * `pretty`: whether the JSON output is pretty-printed (`true` or `false`).
+///   * `fields`: a comma-separated list of the fields to keep in the
+///     structured output formats.
+///   * `color`: whether the text output is colored (`true` or `false`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputSpec {
+    pub format: crate::OutputFormat,
+    pub pretty: Option<bool>,
+    pub fields: Option<Vec<String>>,
+    pub color: Option<bool>,
+}
+
+impl FromStr for OutputSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut parts = spec.split(';').map(str::trim);
+
+        let format = parts.next().unwrap_or_default();
+        if format.is_empty() || format.contains('=') {
+            bail!("the output spec {:?} does not start with an output format", spec);
+        }
+
+        let format = <crate::OutputFormat as clap::ValueEnum>::from_str(format, true)
+            .map_err(|_| anyhow!("unknown output format {:?} in the output spec {:?}", format, spec))?;
+
+        let mut output = OutputSpec {
+            format,
+            pretty: None,
+            fields: None,
+            color: None,
+        };
+
+        for option in parts.filter(|option| !option.is_empty()) {
+            let (key, value) = option
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| anyhow!("the output option {:?} is not of the form key=value", option))?;
+
+            match key {
+                "pretty" => set_option(&mut output.pretty, key, parse_bool(key, value)?)?,
+                "color" => set_option(&mut output.color, key, parse_bool(key, value)?)?,
+                "fields" => {
+                    let fields = value.split(',').map(str::trim).map(String::from).collect::<Vec<String>>();
+                    if fields.iter().any(String::is_empty) {
+                        bail!("the output option fields holds an empty field name");
+                    }
+
+                    set_option(&mut output.fields, key, fields)?
+                }
+                _ => bail!("unknown output option {:?}; expected one of pretty, fields or color", key),
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+fn set_option<T>(option: &mut Option<T>, key: &str, value: T) -> Result<()> {
+    if option.is_some() {
+        bail!("the output option {} is set more than once", key);
+    }
+
+    *option = Some(value);
+    Ok(())
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => bail!("the output option {} expects true or false, got {:?}", key, value),
+    }
+}
+
+/// select_fields keeps only the requested fields of a serialized result.
+///
+/// Objects keep the requested fields, in the order they were requested,
+/// and arrays of objects apply the selection to each of their items. Any
+/// other value is returned as-is, as are all values when no field is requested.
+pub fn select_fields(value: Value, fields: Option<&[String]>) -> Value {
+    let fields = match fields {
+        Some(fields) => fields,
+        None => return value,
+    };
+
+    match value {
+        Value::Object(mut map) => Value::Object(
+            fields
+                .iter()
+                .filter_map(|field| map.remove(field).map(|value| (field.clone(), value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| select_fields(item, Some(fields)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// without_empty strips the fields of a serialized result holding null or
+/// an empty array, at any depth, so that consumers get lean objects.
+///
+/// Only fields are stripped: the items of arrays are kept as-is, so that
+/// their positions are preserved, as is the result itself when empty.
+pub fn without_empty(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, without_empty(value)))
+                .filter(|(_, value)| !is_empty(value))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(without_empty).collect()),
+        value => value,
+    }
+}
+
+/// is_empty returns whether a serialized value is null or an empty array.
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// raw_field extracts a single scalar field of a serialized result, rendered
+/// as a bare token: numbers and booleans as-is, and strings unquoted.
+///
+/// The field is a dot-separated path, such as `time.offset`, in which
+/// numeric segments index arrays, such as `cores.0.usage_percent`.
+///
+/// When `round` is set, numbers are rounded to the nearest integer, as
+/// shell arithmetic only handles integers.
+pub fn raw_field(value: &Value, path: &str, round: bool) -> Result<String> {
+    let field = path.split('.').try_fold(value, |value, segment| match value {
+        Value::Object(map) => map.get(segment),
+        Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    });
+
+    match field {
+        None => bail!("the result has no field {}", path),
+        Some(Value::String(value)) => Ok(value.clone()),
+        Some(Value::Number(value)) if round && !value.is_i64() && !value.is_u64() => {
+            Ok((value.as_f64().unwrap_or_default().round() as i64).to_string())
+        }
+        Some(Value::Number(value)) => Ok(value.to_string()),
+        Some(Value::Bool(value)) => Ok(value.to_string()),
+        Some(Value::Null) => bail!("the field {} has no value", path),
+        Some(_) => bail!("the field {} is not a scalar", path),
+    }
+}
+
+/// with_timestamp stamps a serialized result with the RFC 3339 time it was
+/// captured at, as a leading `captured_at` field.
+///
+/// Objects have the field inserted alongside their own fields. Any other
+/// result, such as an array, cannot hold an extra field, and is wrapped in
+/// an object holding the field and the result, under `data`.
+pub fn with_timestamp(value: Value, captured_at: DateTime<Local>) -> Value {
+    let mut stamped = Map::new();
+    stamped.insert(
+        "captured_at".to_string(),
+        Value::String(captured_at.to_rfc3339_opts(SecondsFormat::Secs, false)),
+    );
+
+    match value {
+        Value::Object(map) => stamped.extend(map),
+        value => {
+            stamped.insert("data".to_string(), value);
+        }
+    }
+
+    Value::Object(stamped)
+}
+
+/// with_tag tags a serialized result with the kind of result it is, as a
+/// leading `type` field, so that a stream of mixed results can be told apart.
+///
+/// Objects have the field inserted alongside their own fields. Any other
+/// result, such as an array, is wrapped in an object holding the field and
+/// the result, under `data`.
+pub fn with_tag(value: Value, tag: &str) -> Value {
+    let mut tagged = Map::new();
+    tagged.insert("type".to_string(), Value::String(tag.to_string()));
+
+    match value {
+        Value::Object(map) => tagged.extend(map),
+        value => {
+            tagged.insert("data".to_string(), value);
+        }
+    }
+
+    Value::Object(tagged)
+}
+
+/// without_tag strips the `type` field a serialized result was tagged with,
+/// provided it holds the expected tag, unwrapping the result it wraps if any.
+pub fn without_tag(value: Value, tag: &str) -> Value {
+    let mut map = match value {
+        Value::Object(map) if map.get("type").and_then(Value::as_str) == Some(tag) => map,
+        value => return value,
+    };
+
+    map.remove("type");
+    if map.contains_key("data") && map.len() == 1 {
+        return map.remove("data").unwrap_or_default();
+    }
+
+    Value::Object(map)
+}
+
+/// unwrap_captured strips the metadata a captured JSON result was wrapped
+/// in, that is the schema version envelope and the `captured_at` timestamp,
+/// leaving the result's own serialized shape.
+pub fn unwrap_captured(value: Value) -> Value {
+    let mut map = match value {
+        Value::Object(map) => map,
+        value => return value,
+    };
+
+    if map.contains_key("schema_version") && map.contains_key("data") && map.len() == 2 {
+        return unwrap_captured(map.remove("data").unwrap_or_default());
+    }
+
+    if map.remove("captured_at").is_some() && map.contains_key("data") && map.len() == 1 {
+        return map.remove("data").unwrap_or_default();
+    }
+
+    Value::Object(map)
+}
+
+/// to_json renders a serialized result as JSON, pretty-printed or compact.
+pub fn to_json<T: Serialize>(value: &T, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+/// to_env renders a serialized result as shell-sourceable `KEY=value` lines.
+///
+/// Objects produce one line per field, and arrays one line per item, each
+/// nested key being appended to the prefix, uppercased, and separated by
+/// underscores. For instance, the first disk's name of a `disks` result
+/// rendered with the `MY_DISK` prefix is output as `MY_DISK_0_NAME=...`.
+///
+/// Values holding characters the shell would interpret are single-quoted.
+pub fn to_env(value: &Value, prefix: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    push_env_lines(&mut lines, value, prefix);
+    lines
+}
+
+fn push_env_lines(lines: &mut Vec<String>, value: &Value, key: &str) {
+    match value {
+        Value::Object(map) => {
+            for (field, value) in map {
+                push_env_lines(lines, value, &format!("{}_{}", key, env_key(field)));
+            }
+        }
+        Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                push_env_lines(lines, value, &format!("{}_{}", key, index));
+            }
+        }
+        Value::Null => lines.push(format!("{}=", key)),
+        Value::String(value) => lines.push(format!("{}={}", key, env_quote(value))),
+        Value::Bool(_) | Value::Number(_) => lines.push(format!("{}={}", key, value)),
+    }
+}
+
+/// env_key converts a field name into an environment variable name component.
+fn env_key(field: &str) -> String {
+    field
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+/// env_quote single-quotes a value if it holds characters the shell would interpret.
+fn env_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-.,:/@%+".contains(c);
+
+    if !value.is_empty() && value.chars().all(is_safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// to_markdown renders a serialized result as a GitHub-flavored Markdown table.
+///
+/// Arrays of objects produce one row per item and one column per field,
+/// arrays of scalars a single value column, and any other result a two-column
+/// table of its fields and values. Nested fields are flattened into dotted
+/// names, such as `date.day_name`.
+///
+/// When a maximum width is provided, such as the terminal's, the widest
+/// columns are narrowed until the rows fit in it, and the cells exceeding
+/// their column's width are truncated with an ellipsis.
+///
+/// An empty array has no columns to render, and renders as nothing.
+pub fn to_markdown(value: &Value, max_width: Option<usize>) -> String {
+    let (header, rows): (Vec<String>, Vec<Vec<String>>) = match value {
+        Value::Array(items) if items.is_empty() => return String::new(),
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            let flattened = items.iter().map(flatten).collect::<Vec<_>>();
+
+            let mut header: Vec<String> = Vec::new();
+            for (field, _) in flattened.iter().flatten() {
+                if !header.contains(field) {
+                    header.push(field.clone());
+                }
+            }
+
+            let rows = flattened
+                .iter()
+                .map(|fields| {
+                    header
+                        .iter()
+                        .map(|column| {
+                            fields
+                                .iter()
+                                .find(|(field, _)| field == column)
+                                .map(|(_, value)| value.clone())
+                                .unwrap_or_default()
+                        })
+                        .collect()
+                })
+                .collect();
+
+            (header, rows)
+        }
+        Value::Array(items) => (
+            vec!["value".to_string()],
+            items.iter().map(|item| vec![scalar_to_string(item)]).collect(),
+        ),
+        _ => (
+            vec!["field".to_string(), "value".to_string()],
+            flatten(value).into_iter().map(|(field, value)| vec![field, value]).collect(),
+        ),
+    };
+
+    let escape = |cells: &[String]| {
+        cells
+            .iter()
+            .map(|cell| cell.replace('|', "\\|").replace('\n', " "))
+            .collect::<Vec<String>>()
+    };
+
+    let header = escape(&header);
+    let rows = rows.iter().map(|row| escape(row)).collect::<Vec<_>>();
+
+    let column_widths = match max_width {
+        Some(max_width) => fit_columns(&header, &rows, max_width),
+        None => vec![usize::MAX; header.len()],
+    };
+
+    let mut lines = vec![
+        markdown_row(&header, &column_widths),
+        markdown_row(&header.iter().map(|_| "---".to_string()).collect::<Vec<_>>(), &column_widths),
+    ];
+    lines.extend(rows.iter().map(|row| markdown_row(row, &column_widths)));
+
+    lines.join("\n")
+}
+
+/// The narrowest a column is allowed to become when fitting a table in a
+/// maximum width, ellipsis included.
+const MIN_COLUMN_WIDTH: usize = 4;
+
+/// fit_columns computes the width of each column so that the table's rows
+/// fit in the maximum width, by repeatedly narrowing the widest column.
+///
+/// Columns are never narrowed below `MIN_COLUMN_WIDTH`, so very narrow
+/// maximum widths can still be exceeded.
+fn fit_columns(header: &[String], rows: &[Vec<String>], max_width: usize) -> Vec<usize> {
+    let mut widths = header.iter().map(|cell| cell.width()).collect::<Vec<usize>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    // Each row is rendered as `| a | b |`: two characters before the first
+    // cell, two after the last one, and three between each pair of cells.
+    let separators = 3 * widths.len() + 1;
+
+    while widths.iter().sum::<usize>() + separators > max_width {
+        match widths.iter_mut().filter(|width| **width > MIN_COLUMN_WIDTH).max() {
+            Some(widest) => *widest -= 1,
+            None => break,
+        }
+    }
+
+    widths
+}
+
+fn markdown_row(cells: &[String], column_widths: &[usize]) -> String {
+    let cells = cells
+        .iter()
+        .zip(column_widths)
+        .map(|(cell, width)| truncate(cell, *width))
+        .collect::<Vec<String>>();
+
+    format!("| {} |", cells.join(" | "))
+}
+
+/// truncate shortens a cell to the provided display width, replacing its
+/// end with an ellipsis.
+///
+/// Widths are measured in terminal columns, so that wide characters, such
+/// as CJK ones, count as two columns.
+fn truncate(cell: &str, width: usize) -> String {
+    if cell.width() <= width {
+        return cell.to_string();
+    }
+
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for c in cell.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if truncated_width + char_width + 1 > width {
+            break;
+        }
+
+        truncated.push(c);
+        truncated_width += char_width;
+    }
+
+    truncated.push('…');
+    truncated
+}
+
+/// flatten collects the scalar fields of a value, naming nested fields
+/// with their dotted path.
+fn flatten(value: &Value) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    push_fields(&mut fields, value, "");
+    fields
+}
+
+fn push_fields(fields: &mut Vec<(String, String)>, value: &Value, path: &str) {
+    let join = |field: &str| {
+        if path.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", path, field)
+        }
+    };
+
+    match value {
+        Value::Object(map) => {
+            for (field, value) in map {
+                push_fields(fields, value, &join(field));
+            }
+        }
+        Value::Array(items) => {
+            for (index, value) in items.iter().enumerate() {
+                push_fields(fields, value, &join(&index.to_string()));
+            }
+        }
+        _ => fields.push((path.to_string(), scalar_to_string(value))),
+    }
+}
+
+/// scalar_to_string renders a scalar value without JSON quoting.
+fn scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn output_spec_parses_the_format_and_options() {
+        let spec = OutputSpec::from_str("JSON; pretty=false; fields=brand, frequency").unwrap();
+
+        assert_eq!(
+            spec,
+            OutputSpec {
+                format: crate::OutputFormat::Json,
+                pretty: Some(false),
+                fields: Some(vec!["brand".to_string(), "frequency".to_string()]),
+                color: None,
+            }
+        );
+    }
+
+    #[test]
+    fn output_spec_rejects_malformed_specs() {
+        for spec in [
+            "",
+            "pretty=false",
+            "yaml",
+            "json;pretty",
+            "json;pretty=yes",
+            "json;pretty=true;pretty=false",
+            "json;fields=brand,,frequency",
+            "json;indent=2",
+        ] {
+            assert!(OutputSpec::from_str(spec).is_err(), "{:?} was accepted", spec);
+        }
+    }
+
+    #[test]
+    fn to_markdown_renders_nothing_for_an_empty_array() {
+        assert_eq!(to_markdown(&json!([]), None), "");
+        assert_eq!(to_markdown(&json!([]), Some(80)), "");
+    }
+
+    #[test]
+    fn to_markdown_renders_one_row_per_item() {
+        let value = json!([{"name": "eth0", "mtu": 1500}, {"name": "lo"}]);
+
+        assert_eq!(
+            to_markdown(&value, None),
+            "| name | mtu |\n| --- | --- |\n| eth0 | 1500 |\n| lo |  |"
+        );
+    }
+
+    #[test]
+    fn raw_field_renders_numbers_as_is() {
+        let value = json!({"used_percent": 9.1, "total_bytes": 16});
+
+        assert_eq!(raw_field(&value, "used_percent", false).unwrap(), "9.1");
+        assert_eq!(raw_field(&value, "total_bytes", false).unwrap(), "16");
+    }
+
+    #[test]
+    fn raw_field_rounds_numbers_to_integers() {
+        let value = json!({"used_percent": 90.6, "offset": -0.4, "total_bytes": 16});
+
+        assert_eq!(raw_field(&value, "used_percent", true).unwrap(), "91");
+        assert_eq!(raw_field(&value, "offset", true).unwrap(), "0");
+        assert_eq!(raw_field(&value, "total_bytes", true).unwrap(), "16");
+    }
+
+    #[test]
+    fn raw_field_renders_strings_unquoted() {
+        let value = json!({"time": {"ntp_server": "pool.ntp.org"}, "cores": [{"name": "cpu0"}]});
+
+        assert_eq!(raw_field(&value, "time.ntp_server", false).unwrap(), "pool.ntp.org");
+        assert_eq!(raw_field(&value, "cores.0.name", true).unwrap(), "cpu0");
+    }
+
+    #[test]
+    fn raw_field_rejects_missing_fields() {
+        let value = json!({"used_percent": 9.1, "cores": [], "label": null});
+
+        assert!(raw_field(&value, "free_percent", false).is_err());
+        assert!(raw_field(&value, "cores.0", false).is_err());
+        assert!(raw_field(&value, "label", false).is_err());
+    }
+
+    #[test]
+    fn without_empty_strips_null_and_empty_array_fields() {
+        let value = json!({
+            "name": "eth0",
+            "gateway": null,
+            "addresses": [],
+            "flags": ["up"],
+            "counters": { "errors": 0, "dropped": null },
+            "items": [null, []],
+        });
+
+        assert_eq!(
+            without_empty(value),
+            json!({
+                "name": "eth0",
+                "flags": ["up"],
+                "counters": { "errors": 0 },
+                "items": [null, []],
+            })
+        );
+        assert_eq!(without_empty(json!([])), json!([]));
+    }
+
+    #[test]
+    fn with_tag_inserts_the_type_into_an_object() {
+        let tagged = with_tag(json!({ "hostname": "box" }), "hostname");
+
+        assert_eq!(tagged, json!({ "type": "hostname", "hostname": "box" }));
+        assert_eq!(tagged.as_object().unwrap().keys().next().unwrap(), "type");
+        assert_eq!(without_tag(tagged, "hostname"), json!({ "hostname": "box" }));
+    }
+
+    #[test]
+    fn with_tag_wraps_an_array_under_data() {
+        let tagged = with_tag(json!([{ "name": "/dev/sda1" }]), "disks");
+
+        assert_eq!(tagged, json!({ "type": "disks", "data": [{ "name": "/dev/sda1" }] }));
+        assert_eq!(without_tag(tagged, "disks"), json!([{ "name": "/dev/sda1" }]));
+    }
+
+    #[test]
+    fn truncate_fits_cells_to_the_width() {
+        assert_eq!(truncate("eth0", 4), "eth0");
+        assert_eq!(truncate("enp3s0f1", 6), "enp3s…");
+        assert_eq!(truncate("日本語日本", 5), "日本…");
+        assert_eq!(truncate("日本語日本", 5).width(), 5);
+    }
+
+    #[test]
+    fn fit_columns_narrows_the_widest_column() {
+        let header = vec!["name".to_string(), "mount_point".to_string()];
+        let rows = vec![vec!["/dev/sda1".to_string(), "/mnt/a/very/long/mount/point".to_string()]];
+
+        let widths = fit_columns(&header, &rows, 30);
+        assert_eq!(widths, vec![9, 14]);
+        assert_eq!(markdown_row(&rows[0], &widths).width(), 30);
+
+        // Columns are never narrowed below the minimum width
+        assert_eq!(fit_columns(&header, &rows, 5), vec![MIN_COLUMN_WIDTH, MIN_COLUMN_WIDTH]);
+    }
+}
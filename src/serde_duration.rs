@@ -0,0 +1,69 @@
+//! Serializes durations as floating-point seconds, such as `1.25`.
+//!
+//! serde's own representation of a `Duration` is a `{secs, nanos}` object,
+//! which is awkward to consume. Every serialized duration goes through this
+//! module instead, along with a field name ending in `_secs`:
+//!
+//! ```
+//! #[serde(rename = "elapsed_secs", with = "crate::serde_duration")]
+//! elapsed: Duration,
+//! ```
+
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Duration::try_from_secs_f64(f64::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+/// A duration serialized as seconds, for the custom serializations that
+/// cannot use this module through an attribute.
+pub struct Secs(pub Duration);
+
+impl Serialize for Secs {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secs {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize(deserializer).map(Secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secs_round_trip_through_seconds() {
+        let serialized = serde_json::to_value(Secs(Duration::from_millis(1250))).unwrap();
+        let deserialized: Secs = serde_json::from_value(serialized.clone()).unwrap();
+
+        assert_eq!(serialized, serde_json::json!(1.25));
+        assert_eq!(deserialized.0, Duration::from_millis(1250));
+    }
+
+    #[test]
+    fn negative_seconds_are_rejected() {
+        assert!(serde_json::from_value::<Secs>(serde_json::json!(-1.0)).is_err());
+    }
+}
@@ -1,12 +1,21 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
+use std::time::{Duration, Instant};
 
+#[cfg(target_os = "linux")]
+use anyhow::Context;
 use anyhow::{Error, Result};
 use colored::*;
 use itertools::Itertools;
-use serde::Serialize;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 use sysinfo::{DiskExt, System, SystemExt};
+use tokio::task::spawn_blocking;
+use unicode_width::UnicodeWidthStr;
 
-use crate::format::human_readable_size;
+use crate::cancel::CancellationToken;
+use crate::format::{human_readable_size, pad_right, Bytes};
+use crate::theme;
 
 /// List all disks and their information
 ///
@@ -21,15 +30,17 @@ use crate::format::human_readable_size;
 /// # Examples
 ///
 /// ```
-/// let disks = storage::list_disks().unwrap();
+/// let disks = storage::list_disks(false).unwrap();
 /// println!("disks: {:?}", disks);
 /// ```
-pub async fn list_disks() -> Result<Vec<DiskInfo>> {
+pub async fn list_disks(smart: bool) -> Result<Vec<DiskInfo>> {
     let mut system = System::new_all();
     system.refresh_disks();
     system.refresh_disks_list();
 
-    system
+    let read_only_mounts = read_only_mount_points();
+
+    let mut disks = system
         .disks()
         .iter()
         .unique_by(|disk| disk.name())
@@ -37,54 +48,750 @@ pub async fn list_disks() -> Result<Vec<DiskInfo>> {
             let name = disk
                 .name()
                 .to_str()
-                .ok_or_else(|| "unknown")
-                .map_err(|e| Error::msg(e))?;
+                .ok_or("unknown")
+                .map_err(Error::msg)?;
+
+            let mount_point = disk.mount_point().to_string_lossy().into_owned();
 
             Ok(DiskInfo {
                 name: name.to_string(),
+                read_only: read_only_mounts.contains(&mount_point),
+                mount_point,
+                label: read_label(name),
                 type_: format!("{:?}", disk.type_()),
-                total_space: disk.total_space(),
-                free_space: disk.available_space(),
+                file_system: String::from_utf8_lossy(disk.file_system()).into_owned(),
+                total_space: Bytes(disk.total_space()),
+                free_space: Bytes(disk.available_space()),
+                is_removable: disk.is_removable(),
+                smart: None,
             })
         })
+        .collect::<Result<Vec<DiskInfo>>>()?;
+
+    if smart {
+        disks = spawn_blocking(move || {
+            // Partitions of the same drive share its health, which is only read once
+            let mut healths: BTreeMap<String, SmartHealth> = BTreeMap::new();
+            for disk in &mut disks {
+                let health = match physical_device(&disk.name) {
+                    Some(device) => healths.entry(device.clone()).or_insert_with(|| read_smart_health(&device)).clone(),
+                    None => SmartHealth::default(),
+                };
+                disk.smart = Some(health);
+            }
+            disks
+        })
+        .await?;
+    }
+
+    Ok(disks)
+}
+
+/// The health of a drive, as reported by its SMART data.
+///
+/// Every field is None when the SMART data cannot be read, such as for
+/// virtual disks, or without the privileges smartctl requires.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SmartHealth {
+    /// The outcome of the drive's self-assessment: PASSED or FAILING.
+    pub status: Option<String>,
+
+    /// The drive's current temperature, in degrees Celsius.
+    pub temperature_celsius: Option<i64>,
+
+    /// The number of hours the drive has been powered on for.
+    pub power_on_hours: Option<u64>,
+}
+
+/// Reads the SMART health of a drive through `smartctl --json`.
+///
+/// smartctl's exit status is a bit mask which is also set for warnings
+/// about healthy drives, so its output is parsed regardless. Any failure,
+/// such as smartctl missing, leaves the health unknown.
+fn read_smart_health(device: &str) -> SmartHealth {
+    let output = match std::process::Command::new("smartctl")
+        .args(["--json", "--health", "--attributes", device])
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return SmartHealth::default(),
+    };
+
+    let report: serde_json::Value = match serde_json::from_slice(&output.stdout) {
+        Ok(report) => report,
+        Err(_) => return SmartHealth::default(),
+    };
+
+    SmartHealth {
+        status: report
+            .pointer("/smart_status/passed")
+            .and_then(serde_json::Value::as_bool)
+            .map(|passed| if passed { "PASSED" } else { "FAILING" }.to_string()),
+        temperature_celsius: report.pointer("/temperature/current").and_then(serde_json::Value::as_i64),
+        power_on_hours: report.pointer("/power_on_time/hours").and_then(serde_json::Value::as_u64),
+    }
+}
+
+/// Returns the device node of the drive a disk's device node belongs to,
+/// such as `/dev/nvme0n1` for `/dev/nvme0n1p2`, from the block devices sysfs
+/// lists. Partitions are nested in their drive's directory.
+#[cfg(target_os = "linux")]
+fn physical_device(device: &str) -> Option<String> {
+    let device = std::fs::canonicalize(device).ok()?;
+    let block = std::path::Path::new("/sys/class/block").join(device.file_name()?);
+
+    if !block.join("partition").exists() {
+        return Some(device.to_string_lossy().into_owned());
+    }
+
+    let drive = std::fs::canonicalize(&block).ok()?;
+    Some(format!("/dev/{}", drive.parent()?.file_name()?.to_string_lossy()))
+}
+
+/// Disks are named after their volume rather than their device node on
+/// other platforms, which cannot be mapped to a drive.
+#[cfg(not(target_os = "linux"))]
+fn physical_device(_device: &str) -> Option<String> {
+    None
+}
+
+/// Reads the label of the filesystem on a device node, from the
+/// `/dev/disk/by-label` links udev maintains.
+#[cfg(target_os = "linux")]
+fn read_label(device: &str) -> Option<String> {
+    let device = std::fs::canonicalize(device).ok()?;
+
+    std::fs::read_dir("/dev/disk/by-label")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .find(|entry| std::fs::canonicalize(entry.path()).ok().as_ref() == Some(&device))
+        // udev escapes the characters unsafe in file names, such as spaces as \x20
+        .map(|entry| entry.file_name().to_string_lossy().replace("\\x20", " "))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_label(_device: &str) -> Option<String> {
+    None
+}
+
+/// Returns the mount points of the filesystems mounted read-only, from the
+/// mount table the kernel exposes in `/proc/mounts`.
+///
+/// A mount point mounted over several times takes the options of the last
+/// mount, which is the one visible.
+#[cfg(target_os = "linux")]
+fn read_only_mount_points() -> BTreeSet<String> {
+    read_only_mount_points_in(&std::fs::read_to_string("/proc/mounts").unwrap_or_default())
+}
+
+/// Returns the mount points mounted read-only in the provided mount table.
+#[cfg(target_os = "linux")]
+fn read_only_mount_points_in(mounts: &str) -> BTreeSet<String> {
+    mounts
+        .lines()
+        .filter_map(parse_mount)
+        .collect::<BTreeMap<String, bool>>()
+        .into_iter()
+        .filter_map(|(mount_point, read_only)| read_only.then_some(mount_point))
         .collect()
 }
 
+/// Parses a line of `/proc/mounts`, such as
+/// `/dev/sda1 /mnt/backup\040disk ext4 ro,relatime 0 0`, into its mount
+/// point and whether it is mounted read-only.
+#[cfg(target_os = "linux")]
+fn parse_mount(line: &str) -> Option<(String, bool)> {
+    let mut fields = line.split_whitespace();
+    let mount_point = fields.nth(1)?;
+    let options = fields.nth(1)?;
+
+    Some((unescape_mount_field(mount_point), options.split(',').any(|option| option == "ro")))
+}
+
+/// Unescapes a field of `/proc/mounts`, in which the kernel escapes
+/// spaces, tabs, newlines and backslashes as octal sequences, such as \040.
+#[cfg(target_os = "linux")]
+fn unescape_mount_field(field: &str) -> String {
+    let mut unescaped = Vec::with_capacity(field.len());
+    let bytes = field.as_bytes();
+
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 4)
+            .filter(|digits| bytes[index] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit)))
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok());
+
+        match escaped {
+            Some(byte) => {
+                unescaped.push(byte);
+                index += 4;
+            }
+            None => {
+                unescaped.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Returns the mount points of the filesystems mounted read-only, from the
+/// mount table `getmntinfo` returns.
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+fn read_only_mount_points() -> BTreeSet<String> {
+    let mut mounts: *mut libc::statfs = std::ptr::null_mut();
+
+    // SAFETY: getmntinfo only writes the address of the mount table to
+    // `mounts`. The table is owned by libc, and reused by later calls, so it
+    // is neither freed nor kept past this function.
+    let count = unsafe { libc::getmntinfo(&mut mounts, libc::MNT_NOWAIT) };
+    if count <= 0 || mounts.is_null() {
+        return BTreeSet::new();
+    }
+
+    // SAFETY: the table is non-null and holds `count` entries, as checked above.
+    let mounts = unsafe { std::slice::from_raw_parts(mounts, count as usize) };
+    mounts
+        .iter()
+        .filter(|mount| mount.f_flags as u64 & libc::MNT_RDONLY as u64 != 0)
+        // SAFETY: the kernel nul-terminates the mount point within its buffer.
+        .map(|mount| unsafe { std::ffi::CStr::from_ptr(mount.f_mntonname.as_ptr()) }.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Returns the root directories of the volumes which are read-only, from
+/// the flags `GetVolumeInformationW` reports for each drive letter in use.
+#[cfg(target_os = "windows")]
+fn read_only_mount_points() -> BTreeSet<String> {
+    const FILE_READ_ONLY_VOLUME: u32 = 0x0008_0000;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetLogicalDrives() -> u32;
+
+        fn GetVolumeInformationW(
+            root_path_name: *const u16,
+            volume_name_buffer: *mut u16,
+            volume_name_size: u32,
+            volume_serial_number: *mut u32,
+            maximum_component_length: *mut u32,
+            file_system_flags: *mut u32,
+            file_system_name_buffer: *mut u16,
+            file_system_name_size: u32,
+        ) -> i32;
+    }
+
+    // SAFETY: GetLogicalDrives takes no arguments, and only returns a bitmask.
+    let drives = unsafe { GetLogicalDrives() };
+
+    (b'A'..=b'Z')
+        .enumerate()
+        .filter(|(index, _)| drives & (1 << index) != 0)
+        .map(|(_, letter)| format!("{}:\\", letter as char))
+        .filter(|root| {
+            let wide_root: Vec<u16> = root.encode_utf16().chain(std::iter::once(0)).collect();
+            let mut flags = 0;
+
+            // SAFETY: the root path is nul-terminated and outlives the call, the
+            // flags point to a live u32, and the buffers not needed are null
+            // with a zero size, which the API accepts.
+            let succeeded = unsafe {
+                GetVolumeInformationW(
+                    wide_root.as_ptr(),
+                    std::ptr::null_mut(),
+                    0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    &mut flags,
+                    std::ptr::null_mut(),
+                    0,
+                )
+            };
+
+            succeeded != 0 && flags & FILE_READ_ONLY_VOLUME != 0
+        })
+        .collect()
+}
+
+/// Mount options cannot be read on other platforms, so no disk is
+/// reported as read-only.
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd", target_os = "windows")))]
+fn read_only_mount_points() -> BTreeSet<String> {
+    BTreeSet::new()
+}
+
 /// Information about a disk
-#[derive(Serialize)]
+///
+/// Besides the total and free space, its serialized form holds the used
+/// space, and the percentage of the total space it represents, rounded to
+/// one decimal.
+///
+/// The disk's name is serialized both as `name` and `device`. On Linux, it
+/// is the device node, such as `/dev/nvme0n1p2`, while on macOS and Windows
+/// it is the volume's name. The filesystem's label is only read on Linux.
+///
+/// Whether the disk is mounted read-only is read from the mount options on
+/// Linux, macOS and FreeBSD, and from the volume's flags on Windows. It is
+/// best-effort: when the options cannot be read, the disk is reported as
+/// writable.
+///
+/// The health of the drive the disk belongs to is only serialized, under
+/// `smart`, when it was requested.
+#[derive(Deserialize)]
 pub struct DiskInfo {
     pub name: String,
 
+    // The path the disk is mounted on, such as / or C:\
+    #[serde(default)]
+    pub mount_point: String,
+
+    // The label of the disk's filesystem, if it has one
+    #[serde(default)]
+    pub label: Option<String>,
+
     #[serde(rename = "type")]
     pub type_: String,
 
+    // The type of the disk's filesystem, such as ext4 or apfs
+    #[serde(default)]
+    pub file_system: String,
+
     #[serde(rename = "total_space_bytes")]
-    pub total_space: u64,
+    pub total_space: Bytes,
 
     #[serde(rename = "free_space_bytes")]
-    pub free_space: u64,
+    pub free_space: Bytes,
+
+    pub is_removable: bool,
+
+    // Whether the disk's filesystem is mounted read-only
+    #[serde(default)]
+    pub read_only: bool,
+
+    // The SMART health of the drive the disk belongs to, if requested
+    #[serde(default)]
+    pub smart: Option<SmartHealth>,
 }
 
-impl Display for DiskInfo {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let free_space = human_readable_size(self.free_space);
-        let total_space = human_readable_size(self.total_space);
-        let free_space_percentage = (self.free_space as f64 / self.total_space as f64 * 100.0).round();
+impl DiskInfo {
+    /// Returns the disk's space in use, in bytes.
+    pub fn used_space(&self) -> Bytes {
+        Bytes(self.total_space.0.saturating_sub(self.free_space.0))
+    }
+
+    /// Returns the percentage of the disk's total space in use.
+    pub fn used_percent(&self) -> f64 {
+        if self.total_space.0 == 0 {
+            return 0.0;
+        }
+
+        self.used_space().0 as f64 / self.total_space.0 as f64 * 100.0
+    }
+
+    /// Returns whether the disk holds writable storage whose free space is
+    /// worth monitoring, rather than a read-only or pseudo filesystem, such
+    /// as the squashfs images of snaps, which are always full.
+    pub fn is_monitored(&self) -> bool {
+        self.total_space.0 > 0 && !self.read_only && !PSEUDO_FILE_SYSTEMS.contains(&self.file_system.as_str())
+    }
+}
+
+/// The filesystems which do not hold persistent storage, or whose capacity
+/// is not the one of a disk, such as memory-backed or image filesystems.
+const PSEUDO_FILE_SYSTEMS: &[&str] = &[
+    "squashfs", "overlay", "tmpfs", "devtmpfs", "ramfs", "proc", "sysfs", "cgroup", "cgroup2", "iso9660", "udf",
+];
+
+impl Serialize for DiskInfo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("DiskInfo", 13)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("device", &self.name)?;
+        state.serialize_field("mount_point", &self.mount_point)?;
+        match &self.label {
+            Some(label) => state.serialize_field("label", label)?,
+            None => state.skip_field("label")?,
+        }
+        state.serialize_field("type", &self.type_)?;
+        state.serialize_field("file_system", &self.file_system)?;
+        state.serialize_field("total_space_bytes", &self.total_space)?;
+        state.serialize_field("free_space_bytes", &self.free_space)?;
+        state.serialize_field("used_space_bytes", &self.used_space())?;
+        state.serialize_field("used_percent", &((self.used_percent() * 10.0).round() / 10.0))?;
+        state.serialize_field("is_removable", &self.is_removable)?;
+        state.serialize_field("read_only", &self.read_only)?;
+        match &self.smart {
+            Some(smart) => state.serialize_field("smart", smart)?,
+            None => state.skip_field("smart")?,
+        }
+        state.end()
+    }
+}
+
+impl DiskInfo {
+    /// Writes the disk's information, padding its name and type to the
+    /// provided widths so that the disks of a list line up.
+    fn write_aligned(&self, f: &mut std::fmt::Formatter<'_>, name_width: usize, type_width: usize) -> std::fmt::Result {
+        let palette = theme::palette();
+        let free_space = human_readable_size(self.free_space.0);
+        let total_space = human_readable_size(self.total_space.0);
+        let free_space_percentage = (self.free_space.0 as f64 / self.total_space.0 as f64 * 100.0).round();
 
         let (colored_free_space, color_free_percentage) = match free_space_percentage {
-            _ if free_space_percentage < 10.0 => (free_space.red(), free_space_percentage.to_string().red()),
-            _ if free_space_percentage < 20.0 => (free_space.yellow(), free_space_percentage.to_string().yellow()),
-            _ => (free_space.green(), free_space_percentage.to_string().green()),
+            _ if free_space_percentage < 10.0 => (free_space.color(palette.critical), free_space_percentage.to_string().color(palette.critical)),
+            _ if free_space_percentage < 20.0 => (free_space.color(palette.warning), free_space_percentage.to_string().color(palette.warning)),
+            _ => (free_space.color(palette.good), free_space_percentage.to_string().color(palette.good)),
         };
 
+        // The name and type are padded before being colored, so that the
+        // escape codes don't count towards their width
+        let name = pad_right(&format!("{},", self.name), name_width + 1);
+        let type_ = pad_right(&format!("{},", self.type_), type_width + 1);
+
         write!(
             f,
-            "{}, {}, {} free of {} ({}% free)",
-            self.name.cyan().bold(),
-            self.type_.bright_white(),
+            "{} {} {} free of {} ({}% free)",
+            name.color(palette.name).bold(),
+            type_.color(palette.muted),
             colored_free_space,
             total_space,
             color_free_percentage
+        )?;
+
+        if self.is_removable {
+            write!(f, " {}", "[removable]".color(palette.muted))?;
+        }
+
+        if self.read_only {
+            write!(f, " {}", "[ro]".color(palette.muted))?;
+        }
+
+        if let Some(smart) = &self.smart {
+            let status = match smart.status.as_deref() {
+                Some("PASSED") => "PASSED".color(palette.good),
+                Some(status) => status.color(palette.critical),
+                None => "unknown".color(palette.muted),
+            };
+            write!(f, " SMART {}", status)?;
+
+            if let Some(temperature) = smart.temperature_celsius {
+                write!(f, ", {}°C", temperature)?;
+            }
+            if let Some(hours) = smart.power_on_hours {
+                write!(f, ", {}h powered on", hours)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for DiskInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_aligned(f, 0, 0)
+    }
+}
+
+/// The disks of the system, displayed one per line with their columns aligned.
+#[derive(Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Disks(pub Vec<DiskInfo>);
+
+impl Display for Disks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name_width = self.0.iter().map(|disk| disk.name.width()).max().unwrap_or(0);
+        let type_width = self.0.iter().map(|disk| disk.type_.width()).max().unwrap_or(0);
+
+        for (index, disk) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            disk.write_aligned(f, name_width, type_width)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Samples the read and write throughput of each disk over the given interval
+///
+/// # Arguments
+///
+/// * `interval` - The duration to sample the disks' IO counters over.
+/// * `cancellation` - A token cutting the sampling short when cancelled.
+///
+/// # Returns
+///
+/// The read and write throughput of each disk, in bytes per second. When
+/// the sampling is cut short, the throughput is averaged over the time
+/// elapsed so far instead.
+///
+/// # Errors
+///
+/// If the disks' IO counters cannot be read, or if the platform does not
+/// expose per-disk IO counters.
+///
+/// # Examples
+///
+/// ```
+/// let disks_io = storage::disks_io(Duration::from_secs(1), &CancellationToken::new()).await.unwrap();
+/// println!("disks io: {:?}", disks_io);
+/// ```
+pub async fn disks_io(interval: Duration, cancellation: &CancellationToken) -> Result<Vec<DiskIo>> {
+    let before = read_disk_io_counters()?;
+    let started_at = Instant::now();
+    cancellation.sleep(interval).await;
+    let after = read_disk_io_counters()?;
+
+    // Cut short or not, the rates are averaged over the time actually elapsed
+    let seconds = started_at.elapsed().as_secs_f64().max(f64::EPSILON);
+
+    Ok(after
+        .into_iter()
+        .filter_map(|counters| {
+            let previous = before.iter().find(|previous| previous.name == counters.name)?;
+
+            Some(DiskIo {
+                name: counters.name,
+                read_bytes_per_sec: (counters.read_bytes.saturating_sub(previous.read_bytes) as f64 / seconds) as u64,
+                write_bytes_per_sec: (counters.written_bytes.saturating_sub(previous.written_bytes) as f64 / seconds) as u64,
+            })
+        })
+        .collect())
+}
+
+/// The read and write throughput of a disk
+#[derive(Deserialize, Serialize)]
+pub struct DiskIo {
+    pub name: String,
+
+    pub read_bytes_per_sec: u64,
+
+    pub write_bytes_per_sec: u64,
+}
+
+impl Display for DiskIo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        write!(
+            f,
+            "{} ↓ {}/s ↑ {}/s",
+            self.name.color(palette.name).bold(),
+            human_readable_size(self.read_bytes_per_sec).color(palette.good),
+            human_readable_size(self.write_bytes_per_sec).color(palette.warning),
         )
     }
 }
+
+/// Reads the total bytes read and written by each disk since boot
+///
+/// Unlike `disks_io`, this reports the raw cumulative counters, and thus
+/// returns immediately rather than sampling over an interval.
+///
+/// # Errors
+///
+/// If the disks' IO counters cannot be read, or if the platform does not
+/// expose per-disk IO counters.
+///
+/// # Examples
+///
+/// ```
+/// let totals = storage::disks_io_totals().await.unwrap();
+/// println!("disks io totals: {:?}", totals);
+/// ```
+pub async fn disks_io_totals() -> Result<Vec<DiskIoTotals>> {
+    Ok(read_disk_io_counters()?
+        .into_iter()
+        .map(|counters| DiskIoTotals {
+            name: counters.name,
+            read_bytes_total: counters.read_bytes,
+            written_bytes_total: counters.written_bytes,
+        })
+        .collect())
+}
+
+/// The total bytes read and written by a disk since boot
+#[derive(Deserialize, Serialize)]
+pub struct DiskIoTotals {
+    pub name: String,
+
+    pub read_bytes_total: u64,
+
+    pub written_bytes_total: u64,
+}
+
+impl Display for DiskIoTotals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        write!(
+            f,
+            "{} ↓ {} ↑ {} since boot",
+            self.name.color(palette.name).bold(),
+            human_readable_size(self.read_bytes_total).color(palette.good),
+            human_readable_size(self.written_bytes_total).color(palette.warning),
+        )
+    }
+}
+
+/// The cumulative IO counters of a disk
+struct DiskIoCounters {
+    name: String,
+    read_bytes: u64,
+    written_bytes: u64,
+}
+
+/// The path of the file exposing the disks' IO counters on Linux
+#[cfg(target_os = "linux")]
+const DISKSTATS_PATH: &str = "/proc/diskstats";
+
+/// Reads the cumulative IO counters of each disk from /proc/diskstats.
+///
+/// Loop and ram devices, as well as devices that never performed any IO,
+/// are skipped.
+#[cfg(target_os = "linux")]
+fn read_disk_io_counters() -> Result<Vec<DiskIoCounters>> {
+    // The kernel always reports sectors as 512 bytes units, regardless
+    // of the device's actual sector size.
+    const SECTOR_SIZE: u64 = 512;
+
+    let content = std::fs::read_to_string(DISKSTATS_PATH)
+        .with_context(|| format!("reading the disks io counters from {} failed", DISKSTATS_PATH))?;
+
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            if fields.len() < 10 {
+                return None;
+            }
+
+            let name = fields[2];
+            if name.starts_with("loop") || name.starts_with("ram") {
+                return None;
+            }
+
+            Some((name, fields[5], fields[9]))
+        })
+        .map(|(name, sectors_read, sectors_written)| {
+            let parse = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .with_context(|| format!("parsing the io counters of disk {} failed", name))
+            };
+
+            Ok(DiskIoCounters {
+                name: name.to_string(),
+                read_bytes: parse(sectors_read)? * SECTOR_SIZE,
+                written_bytes: parse(sectors_written)? * SECTOR_SIZE,
+            })
+        })
+        .filter(|counters| {
+            !matches!(counters, Ok(DiskIoCounters { read_bytes: 0, written_bytes: 0, .. }))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_disk_io_counters() -> Result<Vec<DiskIoCounters>> {
+    Err(crate::error::categorized(
+        crate::error::ErrorCode::UnsupportedPlatform,
+        "per-disk io counters are not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_mount_reads_the_mount_point_and_options() {
+        assert_eq!(parse_mount("/dev/sda1 / ext4 rw,relatime 0 0"), Some(("/".to_string(), false)));
+        assert_eq!(
+            parse_mount("/dev/sdb1 /mnt/backup\\040disk ext4 ro,relatime 0 0"),
+            Some(("/mnt/backup disk".to_string(), true))
+        );
+        assert_eq!(parse_mount("malformed"), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn read_only_mount_points_in_keeps_the_last_mount() {
+        let mounts = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+/dev/sdb1 /mnt/backup\\040disk ext4 ro,relatime 0 0
+/dev/sr0 /media/cdrom iso9660 ro,nosuid 0 0
+/dev/sdc1 /media/cdrom ext4 rw,nosuid 0 0
+";
+
+        assert_eq!(
+            read_only_mount_points_in(mounts),
+            BTreeSet::from(["/mnt/backup disk".to_string()])
+        );
+    }
+
+    fn disk(total_space: u64, free_space: u64) -> DiskInfo {
+        serde_json::from_value(serde_json::json!({
+            "name": "/dev/sda1",
+            "type": "SSD",
+            "total_space_bytes": total_space,
+            "free_space_bytes": free_space,
+            "is_removable": false,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn used_space_is_the_total_minus_the_free_space() {
+        let disk = disk(1000, 250);
+
+        assert_eq!(disk.used_space(), Bytes(750));
+        assert_eq!(disk.used_percent(), 75.0);
+        assert_eq!(serde_json::to_value(&disk).unwrap()["used_space_bytes"], 750);
+    }
+
+    #[test]
+    fn is_removable_is_serialized_and_shown() {
+        let mut disk = disk(1000, 250);
+        assert_eq!(serde_json::to_value(&disk).unwrap()["is_removable"], false);
+
+        disk.is_removable = true;
+        let serialized = serde_json::to_value(&disk).unwrap();
+        assert_eq!(serialized["is_removable"], true);
+        assert!(serde_json::from_value::<DiskInfo>(serialized).unwrap().is_removable);
+        assert!(disk.to_string().contains("[removable]"));
+    }
+
+    #[test]
+    fn disks_columns_line_up() {
+        colored::control::set_override(false);
+
+        let mut nvme = disk(1000, 250);
+        nvme.name = "/dev/nvme0n1p1".to_string();
+        nvme.type_ = "NVMe".to_string();
+        let mut usb = disk(64_000_000, 500);
+        usb.type_ = "Removable".to_string();
+
+        let rendered = Disks(vec![nvme, disk(1000, 900), usb]).to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 3);
+
+        // The name, type and free space columns start where a word follows spaces
+        let column_starts = |line: &str| -> Vec<usize> {
+            line.char_indices()
+                .filter(|(index, character)| *index > 0 && *character != ' ' && line[..*index].ends_with(' '))
+                .map(|(index, _)| index)
+                .take(2)
+                .collect()
+        };
+        let first_row = column_starts(lines[0]);
+        assert!(lines.iter().all(|line| column_starts(line) == first_row), "{}", rendered);
+    }
+}
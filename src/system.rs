@@ -1,11 +1,21 @@
 use std::fmt::Display;
+use std::time::{Duration, Instant};
 
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use anyhow::Context;
 use anyhow::Result;
+use chrono::{DateTime, Local, TimeZone};
 use colored::*;
-use serde::Serialize;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
 use sysinfo::{CpuExt, CpuRefreshKind, RefreshKind, System, SystemExt};
+use tokio::task::spawn_blocking;
 
-use crate::format::human_readable_size;
+use crate::cancel::CancellationToken;
+use crate::format::{human_readable_count, human_readable_size, Bytes, CountStyle, SignedBytes};
+use crate::network;
+use crate::serde_duration::Secs;
+use crate::theme;
 use crate::output::{create_named, Named, NamedKind};
 
 /// returns the hostname of the system as a Named enum
@@ -13,6 +23,142 @@ pub async fn hostname() -> Result<Named> {
     create_named(|| async { whoami::hostname().to_string() }, NamedKind::Hostname).await
 }
 
+/// returns the fully qualified domain name of the system as a Named enum
+///
+/// The FQDN is resolved through a reverse DNS lookup of the system's local IP
+/// address, and thus may hit the network. If the local IP address does not resolve
+/// to a fully qualified name, the short hostname is returned instead.
+pub async fn fqdn() -> Result<Named> {
+    let hostname = whoami::hostname();
+
+    let fqdn = match local_ip_address::local_ip() {
+        Ok(local_ip) => network::reverse_lookup(local_ip)
+            .await?
+            .filter(|name| name.contains('.')),
+        Err(_) => None,
+    };
+
+    create_named(|| async { fqdn.unwrap_or(hostname) }, NamedKind::Hostname).await
+}
+
+/// returns the hostname of the system, along with the other names it is
+/// known by, as resolved by the system's resolver
+///
+/// The hostname is the canonical name the resolver reports for the system's
+/// hostname, and the aliases are the other names listed along with it in
+/// the hosts file. Hosts without a hosts file have no aliases.
+pub async fn hostname_aliases() -> Result<HostnameAliases> {
+    spawn_blocking(|| {
+        let hostname = whoami::hostname();
+        let canonical = canonical_name(&hostname).unwrap_or_else(|| hostname.clone());
+
+        let mut aliases: Vec<String> = Vec::new();
+        for alias in read_host_aliases(&[&hostname, &canonical]) {
+            if !alias.eq_ignore_ascii_case(&canonical) && !aliases.iter().any(|known| known.eq_ignore_ascii_case(&alias)) {
+                aliases.push(alias);
+            }
+        }
+
+        Ok(HostnameAliases {
+            hostname: canonical,
+            aliases,
+        })
+    })
+    .await?
+}
+
+/// Describes the names a system is known by
+#[derive(Deserialize, Serialize)]
+pub struct HostnameAliases {
+    // The canonical name of the system
+    pub hostname: String,
+
+    // The other names of the system, empty if it has none
+    pub aliases: Vec<String>,
+}
+
+impl Display for HostnameAliases {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        write!(f, "{}", self.hostname)?;
+        for alias in &self.aliases {
+            write!(f, "\n{}", alias.color(palette.muted))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves the canonical name of a host through getaddrinfo, as the hosts
+/// file or DNS report it.
+#[cfg(unix)]
+fn canonical_name(host: &str) -> Option<String> {
+    let node = std::ffi::CString::new(host).ok()?;
+
+    // SAFETY: a zeroed addrinfo is a valid set of hints, without any
+    // restriction but the flags set below
+    let mut hints: libc::addrinfo = unsafe { std::mem::zeroed() };
+    hints.ai_flags = libc::AI_CANONNAME;
+    hints.ai_socktype = libc::SOCK_STREAM;
+
+    let mut results: *mut libc::addrinfo = std::ptr::null_mut();
+
+    // SAFETY: the node is NUL-terminated, and the results are only read
+    // when getaddrinfo succeeded, then freed once
+    unsafe {
+        if libc::getaddrinfo(node.as_ptr(), std::ptr::null(), &hints, &mut results) != 0 || results.is_null() {
+            return None;
+        }
+
+        let canonical = (*results).ai_canonname;
+        let name = (!canonical.is_null()).then(|| std::ffi::CStr::from_ptr(canonical).to_string_lossy().into_owned());
+        libc::freeaddrinfo(results);
+
+        name.filter(|name| !name.is_empty())
+    }
+}
+
+#[cfg(not(unix))]
+fn canonical_name(_host: &str) -> Option<String> {
+    None
+}
+
+/// The path of the hosts file on Unix systems
+#[cfg(unix)]
+const HOSTS_PATH: &str = "/etc/hosts";
+
+/// Reads the names listed in the hosts file along with any of the provided
+/// ones, in the order they are listed.
+///
+/// Each line holds an address followed by the names it is known by, such
+/// as `127.0.1.1 host.example.com host`, and may end with a comment.
+#[cfg(unix)]
+fn read_host_aliases(names: &[&str]) -> Vec<String> {
+    let hosts = match std::fs::read_to_string(HOSTS_PATH) {
+        Ok(hosts) => hosts,
+        Err(_) => return Vec::new(),
+    };
+
+    hosts
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .map(|line| line.split_whitespace().skip(1).collect::<Vec<&str>>())
+        .filter(|line_names| {
+            line_names
+                .iter()
+                .any(|line_name| names.iter().any(|name| line_name.eq_ignore_ascii_case(name)))
+        })
+        .flatten()
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn read_host_aliases(_names: &[&str]) -> Vec<String> {
+    Vec::new()
+}
+
 /// returns the username of the system as a Named enum
 pub async fn username() -> Result<Named> {
     create_named(|| async { whoami::username().to_string() }, NamedKind::Username).await
@@ -24,98 +170,1735 @@ pub async fn device_name() -> Result<Named> {
 }
 
 /// returns the operating system of the system as a Named enum
-pub async fn os() -> Result<Named> {
+pub async fn os_name() -> Result<Named> {
     create_named(|| async { whoami::distro().to_string() }, NamedKind::Os).await
 }
 
+/// returns the operating system of the system as an Os struct, its name
+/// and version parsed from the distribution string
+pub async fn os() -> Result<Os> {
+    Ok(Os::parse(&whoami::distro()))
+}
+
+/// Describes an operating system
+#[derive(Deserialize, Serialize)]
+pub struct Os {
+    // The OS's name, such as Ubuntu
+    pub name: String,
+
+    // The OS's version, such as 22.04.3, if the distribution string holds one
+    pub version: Option<String>,
+
+    // The full distribution string, such as Ubuntu 22.04.3 LTS
+    pub distro: String,
+}
+
+impl Os {
+    /// parses a distribution string, such as `Ubuntu 22.04.3 LTS` or
+    /// `Debian GNU/Linux 12 (bookworm)`: the version is the first word
+    /// starting with a digit, optionally prefixed with a `v`, and the name
+    /// is made of the words preceding it
+    fn parse(distro: &str) -> Os {
+        let words = distro.split_whitespace().collect::<Vec<&str>>();
+        let version_index = words.iter().position(|word| {
+            word.trim_start_matches('v')
+                .starts_with(|c: char| c.is_ascii_digit())
+        });
+
+        match version_index {
+            Some(index) if index > 0 => Os {
+                name: words[..index].join(" "),
+                version: Some(words[index].trim_start_matches('v').to_string()),
+                distro: distro.to_string(),
+            },
+            _ => Os {
+                name: distro.to_string(),
+                version: None,
+                distro: distro.to_string(),
+            },
+        }
+    }
+}
+
+impl Display for Os {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.distro)
+    }
+}
+
 /// returns the architecture of the system as a Named enum
-pub async fn architecture() -> Result<Named> {
+pub async fn architecture_name() -> Result<Named> {
     create_named(|| async { whoami::arch().to_string() }, NamedKind::Architecture).await
 }
 
+/// returns the architecture of the system as an Architecture struct
+///
+/// The pointer width and endianness are those of the running binary's
+/// target, which is what matters when picking binaries to run on the system.
+/// When `micro_level` is set, the CPU's microarchitecture level is detected
+/// as well.
+pub async fn architecture(micro_level: bool) -> Result<Architecture> {
+    let pointer_width = if cfg!(target_pointer_width = "64") {
+        64
+    } else if cfg!(target_pointer_width = "32") {
+        32
+    } else {
+        16
+    };
+
+    let endianness = if cfg!(target_endian = "little") { "little" } else { "big" };
+
+    Ok(Architecture {
+        arch: whoami::arch().to_string(),
+        pointer_width,
+        endianness: endianness.to_string(),
+        micro_arch_level: if micro_level { Some(spawn_blocking(micro_arch_level).await?) } else { None },
+    })
+}
+
+/// Describes a CPU architecture
+#[derive(Deserialize, Serialize)]
+pub struct Architecture {
+    // The architecture's name, such as x86_64
+    pub arch: String,
+
+    // The width of pointers in bits: 16, 32 or 64
+    pub pointer_width: u8,
+
+    // The byte order: little or big
+    pub endianness: String,
+
+    // The microarchitecture level, such as x86-64-v3 or armv8, if requested:
+    // null when it does not apply to the architecture, or cannot be detected
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "deserialize_present")]
+    pub micro_arch_level: Option<Option<String>>,
+}
+
+impl Display for Architecture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}-bit, {}-endian", self.arch, self.pointer_width, self.endianness)?;
+
+        match &self.micro_arch_level {
+            Some(Some(level)) => write!(f, ", {})", level.color(theme::palette().name)),
+            Some(None) => write!(f, ", unknown level)"),
+            None => write!(f, ")"),
+        }
+    }
+}
+
+/// Deserializes a field that is present, even as null, as Some, so that
+/// a missing field and a null one are told apart.
+fn deserialize_present<'de, T, D>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Detects the x86-64 psABI microarchitecture level of the CPU, from v1 to
+/// v4, from the features CPUID reports.
+///
+/// The LAHF/SAHF instructions v2 requires are not detectable through the
+/// standard library, and are assumed along with the other v2 features, as
+/// only the earliest x86-64 CPUs lacked them.
+#[cfg(target_arch = "x86_64")]
+fn micro_arch_level() -> Option<String> {
+    macro_rules! detected {
+        ($($feature:tt),*) => {
+            true $(&& std::arch::is_x86_feature_detected!($feature))*
+        };
+    }
+
+    let v2 = detected!("cmpxchg16b", "popcnt", "sse3", "ssse3", "sse4.1", "sse4.2");
+    let v3 = v2 && detected!("avx", "avx2", "bmi1", "bmi2", "f16c", "fma", "lzcnt", "movbe", "xsave");
+    let v4 = v3 && detected!("avx512f", "avx512bw", "avx512cd", "avx512dq", "avx512vl");
+
+    let level = match (v2, v3, v4) {
+        (_, _, true) => 4,
+        (_, true, _) => 3,
+        (true, _, _) => 2,
+        _ => 1,
+    };
+
+    Some(format!("x86-64-v{}", level))
+}
+
+/// Reads the ARM architecture version of the CPU, as listed by the
+/// `CPU architecture` line of /proc/cpuinfo, such as 7 or 8.
+#[cfg(all(target_os = "linux", any(target_arch = "arm", target_arch = "aarch64")))]
+fn micro_arch_level() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok();
+    let version = cpuinfo.as_deref().and_then(|cpuinfo| {
+        cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "CPU architecture").then(|| value.trim().trim_start_matches("AArch").to_string())
+        })
+    });
+
+    match version {
+        Some(version) if !version.is_empty() => Some(format!("armv{}", version)),
+        // Every 64-bit ARM CPU implements at least ARMv8
+        _ => cfg!(target_arch = "aarch64").then(|| "armv8".to_string()),
+    }
+}
+
+/// Every 64-bit ARM CPU implements at least ARMv8, which is as precise as
+/// can be told without reading the system's CPU description.
+#[cfg(all(not(target_os = "linux"), target_arch = "aarch64"))]
+fn micro_arch_level() -> Option<String> {
+    Some("armv8".to_string())
+}
+
+#[cfg(not(any(target_arch = "x86_64", all(target_os = "linux", target_arch = "arm"), target_arch = "aarch64")))]
+fn micro_arch_level() -> Option<String> {
+    None
+}
+
+/// returns the platform the binary runs on as a Platform struct
+///
+/// Unlike the os and architecture, which describe the system, the platform
+/// is the binary's compile-time target, and thus a canonical identifier.
+pub async fn platform() -> Result<Platform> {
+    Ok(Platform {
+        family: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+    })
+}
+
+/// Describes a platform
+#[derive(Deserialize, Serialize)]
+pub struct Platform {
+    // The OS family, such as linux, macos, windows or freebsd
+    pub family: String,
+
+    // The CPU architecture, such as x86_64 or aarch64
+    pub arch: String,
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.family, self.arch)
+    }
+}
+
+/// returns how long the system has been running, and when it booted, as
+/// an Uptime struct
+///
+/// The boot time is the one the kernel reports, rather than the current
+/// time minus the uptime, so that it does not shift when the clock is
+/// adjusted. The latter is only used when the kernel does not report it.
+pub async fn uptime() -> Result<Uptime> {
+    let (uptime, boot_time) = spawn_blocking(|| {
+        let system = System::new();
+        (system.uptime(), system.boot_time())
+    })
+    .await?;
+    let uptime = Duration::from_secs(uptime);
+
+    let boot_time = Some(boot_time)
+        .filter(|boot_time| *boot_time > 0)
+        .and_then(|boot_time| Local.timestamp_opt(boot_time as i64, 0).single())
+        .unwrap_or_else(|| Local::now() - chrono::Duration::from_std(uptime).unwrap_or_else(|_| chrono::Duration::zero()));
+
+    Ok(Uptime { uptime, boot_time })
+}
+
+/// Describes how long a system has been running
+#[derive(Deserialize, Serialize)]
+pub struct Uptime {
+    #[serde(rename = "uptime_secs", with = "crate::serde_duration")]
+    pub uptime: Duration,
+
+    // The time the system booted at, serialized in RFC 3339
+    pub boot_time: DateTime<Local>,
+}
+
+impl Display for Uptime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        write!(
+            f,
+            "up {}, since {}",
+            format_uptime(self.uptime).color(palette.highlight),
+            self.boot_time.format("%Y-%m-%d %H:%M")
+        )
+    }
+}
+
+/// Formats an uptime rounded to the minute, which is as precise as it
+/// matters, such as `3days 4h 12m`.
+pub fn format_uptime(uptime: Duration) -> String {
+    match uptime.as_secs() / 60 {
+        0 => "0m".to_string(),
+        minutes => humantime::format_duration(Duration::from_secs(minutes * 60)).to_string(),
+    }
+}
+
+/// returns whether the system runs inside a container or a virtual machine
+/// as a Virtualization struct
+///
+/// Containers take precedence over virtual machines, as a container's
+/// environment matters more to diagnostics than the host it runs on. The
+/// detection signals are, in order:
+///   * On Linux, the `/.dockerenv` and `/run/.containerenv` files docker and
+///     podman create, the `container` variable container managers set in the
+///     environment of the init process, and the control groups of the init
+///     process, listed in /proc/1/cgroup, such as `/docker/<id>` or
+///     `/kubepods/...`.
+///   * On x86_64, the hypervisor's vendor CPUID reports, when its hypervisor
+///     bit is set, such as `KVMKVMKVM`.
+///   * On Linux, the system's vendor and product names, from the DMI table
+///     under /sys/class/dmi/id, such as `QEMU` or `VMware, Inc.`.
+///   * On macOS, the `kern.hv_vmm_present` sysctl.
+pub async fn virtualization() -> Result<Virtualization> {
+    Ok(spawn_blocking(detect_virtualization).await?)
+}
+
+fn detect_virtualization() -> Virtualization {
+    detect_container()
+        .map(|runtime| Virtualization::container(&runtime))
+        .or_else(cpuid_hypervisor)
+        .or_else(firmware_hypervisor)
+        .unwrap_or_default()
+}
+
+/// Detects the runtime of the container the system runs inside, if any.
+#[cfg(target_os = "linux")]
+fn detect_container() -> Option<String> {
+    container_under(std::path::Path::new("/"))
+}
+
+/// Detects the runtime of the container whose filesystem is rooted at the
+/// provided directory, if any.
+#[cfg(target_os = "linux")]
+fn container_under(root: &std::path::Path) -> Option<String> {
+    if root.join(".dockerenv").exists() {
+        return Some("docker".to_string());
+    }
+
+    if root.join("run/.containerenv").exists() {
+        return Some("podman".to_string());
+    }
+
+    // Reading the init process' environment usually requires privileges
+    let init_environment = std::fs::read(root.join("proc/1/environ")).unwrap_or_default();
+    let container_variable = init_environment
+        .split(|byte| *byte == 0)
+        .find_map(|variable| variable.strip_prefix(b"container="))
+        .map(|runtime| String::from_utf8_lossy(runtime).into_owned())
+        .filter(|runtime| !runtime.is_empty());
+    if container_variable.is_some() {
+        return container_variable;
+    }
+
+    std::fs::read_to_string(root.join("proc/1/cgroup"))
+        .ok()
+        .and_then(|cgroup| container_from_cgroup(&cgroup))
+        .map(str::to_string)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn detect_container() -> Option<String> {
+    None
+}
+
+/// Tells the container runtime from the control groups of a process, as
+/// listed in /proc/<pid>/cgroup: one `id:controllers:path` line per
+/// hierarchy, whose path names the runtime inside containers.
+#[cfg(target_os = "linux")]
+fn container_from_cgroup(cgroup: &str) -> Option<&'static str> {
+    const RUNTIMES: &[(&str, &str)] = &[
+        ("kubepods", "kubernetes"),
+        ("libpod", "podman"),
+        ("docker", "docker"),
+        ("containerd", "containerd"),
+        ("lxc", "lxc"),
+    ];
+
+    cgroup
+        .lines()
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .find_map(|path| {
+            RUNTIMES
+                .iter()
+                .find(|(marker, _)| path.contains(marker))
+                .map(|(_, runtime)| *runtime)
+        })
+}
+
+/// Detects the virtual machine the system runs inside from CPUID, when the
+/// hypervisor bit of its first leaf is set, along with the hypervisor's
+/// vendor its hypervisor leaf reports.
+#[cfg(target_arch = "x86_64")]
+fn cpuid_hypervisor() -> Option<Virtualization> {
+    use std::arch::x86_64::__cpuid;
+
+    // SAFETY: CPUID is available on every x86_64 CPU, and only reads the
+    // CPU's identification. The intrinsic is only safe to call on recent
+    // toolchains, hence the allowance.
+    #[allow(unused_unsafe)]
+    let (features, vendor) = unsafe { (__cpuid(1), __cpuid(0x4000_0000)) };
+
+    // The hypervisor leaf is only meaningful when the hypervisor bit is set
+    if features.ecx & (1 << 31) == 0 {
+        return None;
+    }
+
+    let vendor = [vendor.ebx, vendor.ecx, vendor.edx]
+        .iter()
+        .flat_map(|register| register.to_le_bytes())
+        .collect::<Vec<u8>>();
+
+    Some(Virtualization::vm(hypervisor_from_vendor(&String::from_utf8_lossy(&vendor))))
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn cpuid_hypervisor() -> Option<Virtualization> {
+    None
+}
+
+/// Tells the hypervisor from the vendor signature CPUID reports.
+#[cfg(target_arch = "x86_64")]
+fn hypervisor_from_vendor(vendor: &str) -> Option<&'static str> {
+    const VENDORS: &[(&str, &str)] = &[
+        ("KVMKVMKVM", "kvm"),
+        ("TCGTCGTCGTCG", "qemu"),
+        ("VMwareVMware", "vmware"),
+        ("Microsoft Hv", "hyper-v"),
+        ("XenVMMXenVMM", "xen"),
+        ("VBoxVBoxVBox", "virtualbox"),
+        (" lrpepyh  vr", "parallels"),
+        ("bhyve bhyve ", "bhyve"),
+        ("ACRNACRNACRN", "acrn"),
+        ("QNXQVMBSQG", "qnx"),
+        ("Apple VZ", "apple"),
+    ];
+
+    VENDORS
+        .iter()
+        .find(|(signature, _)| vendor.starts_with(signature))
+        .map(|(_, hypervisor)| *hypervisor)
+}
+
+/// Detects the virtual machine the system runs inside from the system's
+/// vendor and product names, as listed in the DMI table.
+#[cfg(target_os = "linux")]
+fn firmware_hypervisor() -> Option<Virtualization> {
+    const VENDORS: &[(&str, &str)] = &[
+        ("QEMU", "qemu"),
+        ("KVM", "kvm"),
+        ("VMware", "vmware"),
+        ("VirtualBox", "virtualbox"),
+        ("innotek", "virtualbox"),
+        ("Xen", "xen"),
+        ("Parallels", "parallels"),
+        ("Amazon EC2", "amazon"),
+        ("Google Compute Engine", "google"),
+        ("Virtual Machine", "hyper-v"),
+        ("BHYVE", "bhyve"),
+    ];
+
+    let names = ["sys_vendor", "product_name", "board_vendor", "bios_vendor"]
+        .iter()
+        .filter_map(|name| std::fs::read_to_string(format!("/sys/class/dmi/id/{}", name)).ok())
+        .collect::<Vec<String>>();
+
+    VENDORS
+        .iter()
+        .find(|(marker, _)| names.iter().any(|name| name.contains(marker)))
+        .map(|(_, hypervisor)| Virtualization::vm(Some(hypervisor)))
+}
+
+/// Detects whether the system runs inside a virtual machine from the
+/// `kern.hv_vmm_present` sysctl, which does not tell the hypervisor.
+#[cfg(target_os = "macos")]
+fn firmware_hypervisor() -> Option<Virtualization> {
+    let mut present: i32 = 0;
+    let mut len = std::mem::size_of::<i32>();
+
+    // SAFETY: the name is NUL-terminated, and the value is a 32-bit integer
+    let result = unsafe {
+        libc::sysctlbyname(
+            c"kern.hv_vmm_present".as_ptr(),
+            &mut present as *mut i32 as *mut libc::c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+
+    (result == 0 && present == 1).then(|| Virtualization::vm(None))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn firmware_hypervisor() -> Option<Virtualization> {
+    None
+}
+
+/// Describes whether a system runs inside a container or a virtual machine
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Virtualization {
+    // The kind of virtualization: none, container or vm
+    pub kind: VirtualizationKind,
+
+    // The container runtime or hypervisor, such as docker or kvm, if it
+    // can be told
+    pub detail: Option<String>,
+}
+
+impl Virtualization {
+    fn container(runtime: &str) -> Virtualization {
+        Virtualization {
+            kind: VirtualizationKind::Container,
+            detail: Some(runtime.to_string()),
+        }
+    }
+
+    fn vm(hypervisor: Option<&str>) -> Virtualization {
+        Virtualization {
+            kind: VirtualizationKind::Vm,
+            detail: hypervisor.map(str::to_string),
+        }
+    }
+}
+
+impl Display for Virtualization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{} ({})", self.kind, detail),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// The kind of virtualization a system runs inside
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VirtualizationKind {
+    #[default]
+    None,
+    Container,
+    Vm,
+}
+
+impl Display for VirtualizationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VirtualizationKind::None => write!(f, "none"),
+            VirtualizationKind::Container => write!(f, "container"),
+            VirtualizationKind::Vm => write!(f, "vm"),
+        }
+    }
+}
+
 /// returns the CPU of the system as a Cpu struct
-pub async fn cpus() -> Result<Cpu> {
-    let mut system = System::new_with_specifics(RefreshKind::new().with_cpu(CpuRefreshKind::new().with_frequency()));
-    system.refresh_cpu();
+///
+/// When `usage` is set, the usage of each core is measured as well, which
+/// requires sampling the CPUs twice, a short interval apart. When `detailed`
+/// is set, the CPU's caches and features are read as well.
+pub async fn cpus(usage: bool, detailed: bool) -> Result<Cpu> {
+    let mut refresh_kind = CpuRefreshKind::new().with_frequency();
+    if usage {
+        refresh_kind = refresh_kind.with_cpu_usage();
+    }
+
+    let mut system = System::new_with_specifics(RefreshKind::new().with_cpu(refresh_kind));
+    system.refresh_cpu_specifics(refresh_kind);
+
+    if usage {
+        tokio::time::sleep(System::MINIMUM_CPU_UPDATE_INTERVAL).await;
+        system.refresh_cpu_specifics(refresh_kind);
+    }
+
+    let (caches, features) = if detailed {
+        let (caches, features) = spawn_blocking(read_cpu_details).await?;
+        (Some(caches), Some(features))
+    } else {
+        (None, None)
+    };
 
     let cpus = system.cpus();
-    let reference_cpu = cpus.get(0).unwrap();
+    let reference_cpu = cpus.first().unwrap();
 
     Ok(Cpu {
         brand: reference_cpu.brand().to_string(),
         core_count: cpus.len(),
+        physical_cores: system.physical_core_count(),
         frequency: reference_cpu.frequency(),
+        frequency_ghz: (reference_cpu.frequency() as f64 / 10.0).round() / 100.0,
+        cores: cpus
+            .iter()
+            .enumerate()
+            .map(|(index, cpu)| CpuCore {
+                index,
+                frequency_mhz: cpu.frequency(),
+                usage_percent: usage.then(|| (cpu.cpu_usage() as f64 * 10.0).round() / 10.0),
+            })
+            .collect(),
+        caches,
+        features,
+        per_core: false,
     })
 }
 
 /// Describes a CPU
-#[derive(Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Cpu {
     // The CPU's brand
     pub brand: String,
 
-    // The CPU's name
+    // The CPU's number of logical cores, including SMT threads
     pub core_count: usize,
 
+    // The CPU's number of physical cores, if the system reports it
+    #[serde(default)]
+    pub physical_cores: Option<usize>,
+
     // The CPU's frequency in MHz
     pub frequency: u64,
+
+    // The CPU's frequency in GHz, rounded to two decimals
+    pub frequency_ghz: f64,
+
+    // The CPU's cores, in the order the system lists them
+    pub cores: Vec<CpuCore>,
+
+    // The CPU's caches, from the smallest level up, if detailed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caches: Option<Vec<CpuCache>>,
+
+    // The features the CPU supports, such as avx2 or aes, if detailed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+
+    // Whether to display each core in the text output
+    #[serde(skip)]
+    per_core: bool,
+}
+
+impl Cpu {
+    /// sets whether each core is displayed in the text output, below the
+    /// summary. The cores are always serialized.
+    pub fn per_core(mut self, per_core: bool) -> Self {
+        self.per_core = per_core;
+        self
+    }
+}
+
+/// Describes a core of a CPU
+#[derive(Deserialize, Serialize)]
+pub struct CpuCore {
+    // The core's index among the CPU's cores
+    pub index: usize,
+
+    // The core's frequency in MHz
+    pub frequency_mhz: u64,
+
+    // The core's usage, rounded to one decimal, if it was measured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_percent: Option<f64>,
 }
 
 impl Display for Cpu {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}, {} cores running at {} GHz", self.brand.bold(), self.core_count.to_string().cyan(), self.frequency.to_string().green())
+        let palette = theme::palette();
+
+        let core_count = human_readable_count(self.core_count as u64, CountStyle::Separated).color(palette.name);
+        match self.physical_cores {
+            Some(physical_cores) => write!(
+                f,
+                "{}, {} physical / {} logical cores",
+                self.brand.bold(),
+                human_readable_count(physical_cores as u64, CountStyle::Separated).color(palette.name),
+                core_count
+            )?,
+            None => write!(f, "{}, {} cores", self.brand.bold(), core_count)?,
+        }
+        write!(f, " running at {} GHz", self.frequency_ghz.to_string().color(palette.good))?;
+
+        if let Some(caches) = &self.caches {
+            let caches = caches.iter().map(ToString::to_string).collect::<Vec<String>>();
+            write!(f, "\ncaches: {}", if caches.is_empty() { "unknown".to_string() } else { caches.join(", ") })?;
+        }
+
+        if let Some(features) = &self.features {
+            write!(f, "\nfeatures: {}", if features.is_empty() { "unknown".to_string() } else { features.join(" ") })?;
+        }
+
+        if !self.per_core {
+            return Ok(());
+        }
+
+        for core in &self.cores {
+            write!(
+                f,
+                "\ncore {}: {:.2} GHz",
+                core.index.to_string().color(palette.name),
+                core.frequency_mhz as f64 / 1000.0
+            )?;
+
+            if let Some(usage_percent) = core.usage_percent {
+                write!(f, ", {}% used", format!("{:.1}", usage_percent).color(palette.highlight))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Describes a cache of a CPU
+#[derive(Deserialize, Serialize)]
+pub struct CpuCache {
+    // The cache's level, such as 1 for the L1 cache
+    pub level: u8,
+
+    // The kind of data the cache holds: data, instruction or unified
+    pub kind: String,
+
+    // The cache's size
+    #[serde(rename = "size_bytes")]
+    pub size: Bytes,
+}
+
+impl Display for CpuCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let suffix = match self.kind.as_str() {
+            "data" => "d",
+            "instruction" => "i",
+            _ => "",
+        };
+
+        write!(f, "L{}{} {}", self.level, suffix, human_readable_size(self.size.0))
     }
 }
 
+/// returns the caches of the CPU, and the features it supports, such as
+/// avx2 or aes. Either is empty when it cannot be read.
+fn read_cpu_details() -> (Vec<CpuCache>, Vec<String>) {
+    (read_cpu_caches(), read_cpu_features())
+}
+
+/// The directory holding the caches of the first CPU on Linux
+#[cfg(target_os = "linux")]
+const CPU_CACHE_DIR: &str = "/sys/devices/system/cpu/cpu0/cache";
+
+/// Reads the caches of the first CPU, one `index*` directory per cache, each
+/// holding its level, type and size, such as `48K`.
+#[cfg(target_os = "linux")]
+fn read_cpu_caches() -> Vec<CpuCache> {
+    let entries = match std::fs::read_dir(CPU_CACHE_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let read = |dir: &std::path::Path, name: &str| std::fs::read_to_string(dir.join(name)).ok().map(|value| value.trim().to_string());
+
+    let mut caches = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("index"))
+        .filter_map(|entry| {
+            let dir = entry.path();
+            let level = read(&dir, "level")?.parse().ok()?;
+            let kind = read(&dir, "type")?.to_lowercase();
+            let size = read(&dir, "size")?;
+            let size = match size.strip_suffix('K') {
+                Some(kib) => kib.parse::<u64>().ok()? * 1024,
+                None => match size.strip_suffix('M') {
+                    Some(mib) => mib.parse::<u64>().ok()? * 1024 * 1024,
+                    None => size.parse().ok()?,
+                },
+            };
+
+            Some(CpuCache { level, kind, size: Bytes(size) })
+        })
+        .collect::<Vec<CpuCache>>();
+
+    caches.sort_by(|a, b| (a.level, &a.kind).cmp(&(b.level, &b.kind)));
+    caches
+}
+
+/// Reads the features of the CPU, as listed by the `flags` line of
+/// /proc/cpuinfo on x86, and its `Features` line on ARM.
+#[cfg(target_os = "linux")]
+fn read_cpu_features() -> Vec<String> {
+    std::fs::read_to_string("/proc/cpuinfo")
+        .ok()
+        .and_then(|cpuinfo| {
+            cpuinfo.lines().find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                matches!(key.trim(), "flags" | "Features")
+                    .then(|| value.split_whitespace().map(str::to_string).collect())
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the caches of the CPU from the `hw.*cachesize` sysctls.
+#[cfg(target_os = "macos")]
+fn read_cpu_caches() -> Vec<CpuCache> {
+    [
+        (1, "data", "hw.l1dcachesize\0"),
+        (1, "instruction", "hw.l1icachesize\0"),
+        (2, "unified", "hw.l2cachesize\0"),
+        (3, "unified", "hw.l3cachesize\0"),
+    ]
+    .into_iter()
+    .filter_map(|(level, kind, name)| {
+        let mut size: i64 = 0;
+        let mut len = std::mem::size_of::<i64>();
+
+        // SAFETY: the name is NUL-terminated, and the value is a 64-bit
+        // integer, as documented by the sysctls
+        let result = unsafe {
+            libc::sysctlbyname(
+                name.as_ptr() as *const libc::c_char,
+                &mut size as *mut i64 as *mut libc::c_void,
+                &mut len,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+
+        // Missing caches, such as the L3 cache of Apple silicon, are reported
+        // as failures or zero sizes
+        (result == 0 && size > 0).then(|| CpuCache {
+            level,
+            kind: kind.to_string(),
+            size: Bytes(size as u64),
+        })
+    })
+    .collect()
+}
+
+/// Reads the features of the CPU from the `machdep.cpu.*features` sysctls,
+/// which only Intel CPUs report.
+#[cfg(target_os = "macos")]
+fn read_cpu_features() -> Vec<String> {
+    ["machdep.cpu.features\0", "machdep.cpu.leaf7_features\0"]
+        .into_iter()
+        .filter_map(|name| {
+            let mut buffer = [0u8; 2048];
+            let mut len = buffer.len();
+
+            // SAFETY: the name is NUL-terminated, and the buffer's length is
+            // passed along, so that the value is truncated rather than overflowing
+            let result = unsafe {
+                libc::sysctlbyname(
+                    name.as_ptr() as *const libc::c_char,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    &mut len,
+                    std::ptr::null_mut(),
+                    0,
+                )
+            };
+
+            (result == 0).then(|| c_chars_to_string(&buffer[..len])).flatten()
+        })
+        .flat_map(|features| {
+            features
+                .split_whitespace()
+                .map(|feature| feature.to_lowercase())
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_cpu_caches() -> Vec<CpuCache> {
+    Vec::new()
+}
+
+/// Detects the features of the CPU through CPUID, which is only portable
+/// to x86 CPUs.
+#[cfg(all(not(any(target_os = "linux", target_os = "macos")), target_arch = "x86_64"))]
+fn read_cpu_features() -> Vec<String> {
+    macro_rules! detect {
+        ($($feature:tt),*) => {
+            vec![$((std::arch::is_x86_feature_detected!($feature), $feature)),*]
+        };
+    }
+
+    detect!("sse", "sse2", "sse3", "ssse3", "sse4.1", "sse4.2", "popcnt", "aes", "pclmulqdq", "avx", "avx2", "fma", "bmi1", "bmi2", "avx512f", "sha")
+        .into_iter()
+        .filter(|(detected, _)| *detected)
+        .map(|(_, feature)| feature.to_string())
+        .collect()
+}
+
+#[cfg(all(not(any(target_os = "linux", target_os = "macos")), not(target_arch = "x86_64")))]
+fn read_cpu_features() -> Vec<String> {
+    Vec::new()
+}
+
 /// returns the RAM of the system as a Ram struct
-pub async fn ram() -> Result<Ram> {
+///
+/// When `detailed` is set, the RAM used by the page cache and the buffers
+/// is read as well, which is only supported on Linux.
+pub async fn ram(detailed: bool) -> Result<Ram> {
     let mut system = System::new_with_specifics(RefreshKind::new().with_memory());
     system.refresh_memory();
 
+    let (cached, buffers) = if detailed {
+        spawn_blocking(read_ram_caches).await?
+    } else {
+        (None, None)
+    };
+
     Ok(Ram {
-        total: system.total_memory(),
-        used: system.used_memory(),
-        free: system.free_memory(),
-        available: system.available_memory(),
+        total: Bytes(system.total_memory()),
+        used: Bytes(system.used_memory()),
+        free: Bytes(system.free_memory()),
+        available: Bytes(system.available_memory()),
+        cached: cached.map(Bytes),
+        buffers: buffers.map(Bytes),
     })
 }
 
+/// Reads the RAM used by the page cache and the buffers, from the `Cached`
+/// and `Buffers` lines of /proc/meminfo, such as `Cached: 2097152 kB`,
+/// wherever they are listed. Either is None when it cannot be read.
+#[cfg(target_os = "linux")]
+fn read_ram_caches() -> (Option<u64>, Option<u64>) {
+    let meminfo = match std::fs::read_to_string("/proc/meminfo") {
+        Ok(meminfo) => meminfo,
+        Err(_) => return (None, None),
+    };
+
+    let read = |name: &str| {
+        meminfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            if key.trim() != name {
+                return None;
+            }
+
+            let mut words = value.split_whitespace();
+            let amount = words.next()?.parse::<u64>().ok()?;
+            match words.next() {
+                Some(unit) if unit.eq_ignore_ascii_case("kb") => Some(amount * 1024),
+                Some(_) => None,
+                None => Some(amount),
+            }
+        })
+    };
+
+    (read("Cached"), read("Buffers"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_ram_caches() -> (Option<u64>, Option<u64>) {
+    (None, None)
+}
+
 /// Describes the RAM of a system
-#[derive(Serialize)]
+///
+/// Besides the byte counts, its serialized form holds the percentages of
+/// the installed RAM in use and available, rounded to one decimal. The RAM
+/// used by the page cache and the buffers is only serialized when it was
+/// read.
+#[derive(Deserialize)]
 pub struct Ram {
     #[serde(rename = "total_ram_bytes")]
-    pub total: u64,
+    pub total: Bytes,
 
     #[serde(rename = "used_ram_bytes")]
-    pub used: u64,
+    pub used: Bytes,
 
     #[serde(rename = "free_ram_bytes")]
-    pub free: u64,
+    pub free: Bytes,
 
     #[serde(rename = "available_ram_bytes")]
-    pub available: u64,
+    pub available: Bytes,
+
+    // The RAM used by the page cache, if detailed, on Linux
+    #[serde(rename = "cached_bytes", default)]
+    pub cached: Option<Bytes>,
+
+    // The RAM used by the kernel's buffers, if detailed, on Linux
+    #[serde(rename = "buffers_bytes", default)]
+    pub buffers: Option<Bytes>,
+}
+
+impl Ram {
+    /// Returns the percentage of the installed RAM in use.
+    pub fn used_percent(&self) -> f64 {
+        percent_of(self.used.0, self.total.0)
+    }
+
+    /// Returns the percentage of the installed RAM available for new processes.
+    pub fn available_percent(&self) -> f64 {
+        percent_of(self.available.0, self.total.0)
+    }
+}
+
+/// returns the percentage a part represents of a total, or zero when the total is zero
+fn percent_of(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    part as f64 / total as f64 * 100.0
+}
+
+impl Serialize for Ram {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let round = |percent: f64| (percent * 10.0).round() / 10.0;
+
+        let mut state = serializer.serialize_struct("Ram", 8)?;
+        state.serialize_field("total_ram_bytes", &self.total)?;
+        state.serialize_field("used_ram_bytes", &self.used)?;
+        state.serialize_field("free_ram_bytes", &self.free)?;
+        state.serialize_field("available_ram_bytes", &self.available)?;
+        state.serialize_field("used_percent", &round(self.used_percent()))?;
+        state.serialize_field("available_percent", &round(self.available_percent()))?;
+        match self.cached {
+            Some(cached) => state.serialize_field("cached_bytes", &cached)?,
+            None => state.skip_field("cached_bytes")?,
+        }
+        match self.buffers {
+            Some(buffers) => state.serialize_field("buffers_bytes", &buffers)?,
+            None => state.skip_field("buffers_bytes")?,
+        }
+        state.end()
+    }
 }
 
 impl Display for Ram {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let total = human_readable_size(self.total);
-        let used = human_readable_size(self.used);
-        let used_percentage = (self.used as f64 / self.total as f64) * 100.0;
-
-        println!("used percentage: {}", used_percentage);
+        let palette = theme::palette();
+        let total = human_readable_size(self.total.0);
+        let used = human_readable_size(self.used.0);
+        let used_percentage = self.used_percent();
 
         let (used_colored, used_percentage_colored) = match used_percentage {
-            _ if used_percentage > 90.0 => (used.red(), format!("{:.1}", used_percentage).to_string().red()),
-            _ if used_percentage > 70.0 => (used.yellow(), format!("{:.1}", used_percentage).to_string().yellow()),
-            _ => (used.green(), format!("{:.1}", used_percentage).to_string().green()),
+            _ if used_percentage > 90.0 => (used.color(palette.critical), format!("{:.1}", used_percentage).to_string().color(palette.critical)),
+            _ if used_percentage > 70.0 => (used.color(palette.warning), format!("{:.1}", used_percentage).to_string().color(palette.warning)),
+            _ => (used.color(palette.good), format!("{:.1}", used_percentage).to_string().color(palette.good)),
         };
 
         write!(
             f,
-            "{} installed, {} in use ({}%)",
+            "{} installed, {} in use ({}%), {} available",
             total.bold(),
             used_colored,
             used_percentage_colored,
+            human_readable_size(self.available.0),
+        )?;
+
+        // The page cache and buffers are part of the available RAM, as the
+        // kernel reclaims them when processes need memory
+        if self.cached.is_some() || self.buffers.is_some() {
+            let cache = self.cached.unwrap_or_default().0 + self.buffers.unwrap_or_default().0;
+            write!(f, " (incl. {} cache)", human_readable_size(cache).color(palette.muted))?;
+        }
+
+        // The available RAM accounts for the reclaimable caches, so that a
+        // low figure means new processes are about to run out of memory
+        let available_percentage = self.available_percent();
+        if available_percentage < LOW_AVAILABLE_RAM_PERCENT {
+            write!(
+                f,
+                "\n{}",
+                format!("warning: only {:.1}% of the RAM is available", available_percentage).color(palette.critical)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The percentage of available RAM below which the text output warns
+const LOW_AVAILABLE_RAM_PERCENT: f64 = 5.0;
+
+/// returns the trend of the RAM in use, sampled `count` times, `interval` apart
+///
+/// Sampling stops early when the token is cancelled, in which case the
+/// trend covers the samples taken so far.
+pub async fn ram_trend(count: u64, interval: Duration, cancellation: &CancellationToken) -> Result<RamTrend> {
+    let start = Instant::now();
+    let mut samples = Vec::new();
+
+    for index in 0..count {
+        samples.push(RamSample {
+            elapsed: start.elapsed(),
+            used: ram(false).await?.used,
+        });
+
+        if index + 1 < count && !cancellation.sleep(interval).await {
+            break;
+        }
+    }
+
+    Ok(RamTrend { samples })
+}
+
+/// A sample of the RAM in use
+#[derive(Deserialize, Serialize)]
+pub struct RamSample {
+    #[serde(rename = "elapsed_secs", with = "crate::serde_duration")]
+    pub elapsed: Duration,
+
+    #[serde(rename = "used_ram_bytes")]
+    pub used: Bytes,
+}
+
+/// Describes the trend of the RAM in use over a series of samples
+///
+/// Its serialized form holds the first, last, minimum and maximum RAM in
+/// use, the difference between the last and first, and the raw samples.
+#[derive(Deserialize)]
+pub struct RamTrend {
+    pub samples: Vec<RamSample>,
+}
+
+impl RamTrend {
+    /// returns the RAM in use of the first sample
+    pub fn first(&self) -> u64 {
+        self.samples.first().map(|sample| sample.used.0).unwrap_or(0)
+    }
+
+    /// returns the RAM in use of the last sample
+    pub fn last(&self) -> u64 {
+        self.samples.last().map(|sample| sample.used.0).unwrap_or(0)
+    }
+
+    /// returns the lowest RAM in use among the samples
+    pub fn min(&self) -> u64 {
+        self.samples.iter().map(|sample| sample.used.0).min().unwrap_or(0)
+    }
+
+    /// returns the highest RAM in use among the samples
+    pub fn max(&self) -> u64 {
+        self.samples.iter().map(|sample| sample.used.0).max().unwrap_or(0)
+    }
+
+    /// returns how much the RAM in use grew between the first and last samples,
+    /// negative if it shrank
+    pub fn delta(&self) -> i64 {
+        self.last() as i64 - self.first() as i64
+    }
+
+    /// returns the time elapsed between the first and last samples
+    pub fn duration(&self) -> Duration {
+        self.samples.last().map(|sample| sample.elapsed).unwrap_or_default()
+    }
+}
+
+impl Serialize for RamTrend {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("RamTrend", 7)?;
+        state.serialize_field("first_used_ram_bytes", &Bytes(self.first()))?;
+        state.serialize_field("last_used_ram_bytes", &Bytes(self.last()))?;
+        state.serialize_field("min_used_ram_bytes", &Bytes(self.min()))?;
+        state.serialize_field("max_used_ram_bytes", &Bytes(self.max()))?;
+        state.serialize_field("delta_used_ram_bytes", &SignedBytes(self.delta()))?;
+        state.serialize_field("duration_secs", &Secs(self.duration()))?;
+        state.serialize_field("samples", &self.samples)?;
+        state.end()
+    }
+}
+
+impl Display for RamTrend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+        let delta = human_readable_size(self.delta().unsigned_abs());
+        // Round the duration to the second, or to the millisecond below one second
+        let duration = self.duration();
+        let over = match duration {
+            _ if duration < Duration::from_secs(1) => humantime::format_duration(Duration::from_millis(duration.as_millis() as u64)),
+            _ => humantime::format_duration(Duration::from_secs(duration.as_secs_f64().round() as u64)),
+        };
+
+        let change = match self.delta() {
+            0 => "used stayed the same".to_string(),
+            d if d > 0 => format!("used grew {}", delta.color(palette.warning)),
+            _ => format!("used shrank {}", delta.color(palette.good)),
+        };
+
+        write!(
+            f,
+            "{} over {} ({} → {})",
+            change,
+            over,
+            human_readable_size(self.first()).bold(),
+            human_readable_size(self.last()).bold(),
+        )
+    }
+}
+/// returns the users currently logged in on the system
+///
+/// # Errors
+///
+/// If the system's login records cannot be read, or if the platform
+/// is not supported.
+pub async fn users() -> Result<Vec<LoggedInUser>> {
+    spawn_blocking(read_logged_in_users).await?
+}
+
+/// Describes a user currently logged in on the system
+#[derive(Deserialize, Serialize)]
+pub struct LoggedInUser {
+    // The user's login name
+    pub name: String,
+
+    // The terminal the user is logged in on
+    pub tty: Option<String>,
+
+    // The remote host the user is logged in from
+    pub from: Option<String>,
+
+    // The time at which the user logged in
+    pub login_time: Option<DateTime<Local>>,
+}
+
+impl Display for LoggedInUser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        write!(f, "{}", self.name.bold())?;
+        write!(f, "\t{}", self.tty.as_deref().unwrap_or("-").color(palette.name))?;
+
+        if let Some(login_time) = &self.login_time {
+            write!(f, "\t{}", login_time.format("%Y-%m-%d %H:%M"))?;
+        }
+
+        if let Some(from) = &self.from {
+            write!(f, "\t({})", from)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The path of the utmp file holding the login records on Linux
+#[cfg(target_os = "linux")]
+const UTMP_PATH: &str = "/var/run/utmp";
+
+/// Reads the logged-in users from the utmp file.
+///
+/// The file is a sequence of fixed-size `struct utmp` records, as laid out
+/// by glibc. Only the `USER_PROCESS` records describe logged-in users.
+#[cfg(target_os = "linux")]
+fn read_logged_in_users() -> Result<Vec<LoggedInUser>> {
+    const RECORD_SIZE: usize = 384;
+    const USER_PROCESS: i16 = 7;
+
+    let data = std::fs::read(UTMP_PATH)
+        .with_context(|| format!("reading the login records from {} failed", UTMP_PATH))?;
+
+    let users = data
+        .chunks_exact(RECORD_SIZE)
+        .filter(|record| i16::from_ne_bytes([record[0], record[1]]) == USER_PROCESS)
+        .map(|record| {
+            let seconds = i32::from_ne_bytes(record[340..344].try_into().unwrap());
+            let microseconds = i32::from_ne_bytes(record[344..348].try_into().unwrap());
+
+            LoggedInUser {
+                name: c_chars_to_string(&record[44..76]).unwrap_or_default(),
+                tty: c_chars_to_string(&record[8..40]),
+                from: c_chars_to_string(&record[76..332]),
+                login_time: Local
+                    .timestamp_opt(seconds as i64, microseconds as u32 * 1000)
+                    .single(),
+            }
+        })
+        .collect();
+
+    Ok(users)
+}
+
+/// Reads the logged-in users from the utmpx database.
+#[cfg(target_os = "macos")]
+fn read_logged_in_users() -> Result<Vec<LoggedInUser>> {
+    let mut users = Vec::new();
+
+    // SAFETY: the utmpx database is only accessed from this thread, and each
+    // entry returned by getutxent is copied before the next call.
+    unsafe {
+        libc::setutxent();
+
+        loop {
+            let entry = libc::getutxent();
+            if entry.is_null() {
+                break;
+            }
+
+            let entry = &*entry;
+            if entry.ut_type != libc::USER_PROCESS {
+                continue;
+            }
+
+            let to_bytes = |chars: &[libc::c_char]| chars.iter().map(|c| *c as u8).collect::<Vec<u8>>();
+
+            users.push(LoggedInUser {
+                name: c_chars_to_string(&to_bytes(&entry.ut_user)).unwrap_or_default(),
+                tty: c_chars_to_string(&to_bytes(&entry.ut_line)),
+                from: c_chars_to_string(&to_bytes(&entry.ut_host)),
+                login_time: Local
+                    .timestamp_opt(entry.ut_tv.tv_sec, entry.ut_tv.tv_usec as u32 * 1000)
+                    .single(),
+            });
+        }
+
+        libc::endutxent();
+    }
+
+    Ok(users)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_logged_in_users() -> Result<Vec<LoggedInUser>> {
+    Err(crate::error::categorized(
+        crate::error::ErrorCode::UnsupportedPlatform,
+        "listing logged-in users is not supported on this platform",
+    ))
+}
+
+/// Converts a NUL-padded C character array to a String,
+/// returning None if it is empty.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn c_chars_to_string(chars: &[u8]) -> Option<String> {
+    let end = chars.iter().position(|c| *c == 0).unwrap_or(chars.len());
+    let value = String::from_utf8_lossy(&chars[..end]).trim().to_string();
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// returns the memory pressure of the system: the share of time tasks were
+/// stalled waiting for memory on Linux, and the pressure level on macOS
+///
+/// Unlike the free RAM, which caches make look low on healthy systems,
+/// the pressure tells whether the system actually lacks memory.
+///
+/// # Errors
+///
+/// If the pressure cannot be read, or if the platform is not supported.
+pub async fn memory_pressure() -> Result<MemoryPressure> {
+    spawn_blocking(read_memory_pressure).await?
+}
+
+/// Describes the memory pressure of a system
+///
+/// On Linux it holds the pressure stall information (PSI) of the memory.
+/// On macOS it holds the pressure level the kernel reports.
+#[derive(Deserialize, Serialize)]
+pub struct MemoryPressure {
+    // The pressure level: normal, warning or critical, on macOS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+
+    // The share of time at least some tasks were stalled waiting for memory, on Linux
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub some: Option<PressureStall>,
+
+    // The share of time all the non-idle tasks were stalled waiting for memory, on Linux
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full: Option<PressureStall>,
+}
+
+/// Describes the share of time tasks were stalled waiting for a resource
+#[derive(Deserialize, Serialize)]
+pub struct PressureStall {
+    // The percentage of time stalled, averaged over the last 10 seconds
+    pub avg10: f64,
+
+    // The percentage of time stalled, averaged over the last 60 seconds
+    pub avg60: f64,
+
+    // The percentage of time stalled, averaged over the last 300 seconds
+    pub avg300: f64,
+
+    // The total time stalled since boot, in microseconds
+    pub total_us: u64,
+}
+
+impl Display for MemoryPressure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let palette = theme::palette();
+
+        if let Some(level) = &self.level {
+            let color = match level.as_str() {
+                "critical" => palette.critical,
+                "warning" => palette.warning,
+                _ => palette.good,
+            };
+
+            return write!(f, "memory pressure: {}", level.color(color));
+        }
+
+        let colored = |stall: &Option<PressureStall>| {
+            let avg10 = stall.as_ref().map(|stall| stall.avg10).unwrap_or(0.0);
+            let color = match avg10 {
+                _ if avg10 > 10.0 => palette.critical,
+                _ if avg10 > 1.0 => palette.warning,
+                _ => palette.good,
+            };
+
+            format!("{:.1}%", avg10).color(color)
+        };
+
+        write!(
+            f,
+            "memory pressure: some {} / full {} (10s avg)",
+            colored(&self.some),
+            colored(&self.full)
         )
     }
-}
\ No newline at end of file
+}
+
+/// The path of the memory's pressure stall information on Linux
+#[cfg(target_os = "linux")]
+const MEMORY_PSI_PATH: &str = "/proc/pressure/memory";
+
+/// Reads the memory's pressure stall information.
+#[cfg(target_os = "linux")]
+fn read_memory_pressure() -> Result<MemoryPressure> {
+    let data = std::fs::read_to_string(MEMORY_PSI_PATH).with_context(|| {
+        format!(
+            "reading {} failed; pressure stall information requires Linux 4.20 or later",
+            MEMORY_PSI_PATH
+        )
+    })?;
+
+    parse_memory_pressure(&data).with_context(|| format!("parsing {} failed", MEMORY_PSI_PATH))
+}
+
+/// Parses pressure stall information, which holds a line per kind of
+/// stall, such as: `some avg10=0.00 avg60=0.00 avg300=0.00 total=0`.
+#[cfg(target_os = "linux")]
+fn parse_memory_pressure(data: &str) -> Result<MemoryPressure> {
+    let mut pressure = MemoryPressure {
+        level: None,
+        some: None,
+        full: None,
+    };
+
+    for line in data.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next();
+
+        let mut stall = PressureStall {
+            avg10: 0.0,
+            avg60: 0.0,
+            avg300: 0.0,
+            total_us: 0,
+        };
+        for field in fields {
+            let (key, value) = field
+                .split_once('=')
+                .with_context(|| format!("malformed field {:?}", field))?;
+            let invalid = || format!("invalid {} value {:?}", key, value);
+
+            match key {
+                "avg10" => stall.avg10 = value.parse().with_context(invalid)?,
+                "avg60" => stall.avg60 = value.parse().with_context(invalid)?,
+                "avg300" => stall.avg300 = value.parse().with_context(invalid)?,
+                "total" => stall.total_us = value.parse().with_context(invalid)?,
+                // Fields added by later kernels are ignored
+                _ => {}
+            }
+        }
+
+        match kind {
+            Some("some") => pressure.some = Some(stall),
+            Some("full") => pressure.full = Some(stall),
+            _ => {}
+        }
+    }
+
+    Ok(pressure)
+}
+
+/// Reads the memory pressure level the kernel reports.
+#[cfg(target_os = "macos")]
+fn read_memory_pressure() -> Result<MemoryPressure> {
+    let mut level: libc::c_int = 0;
+    let mut size = std::mem::size_of::<libc::c_int>();
+
+    // SAFETY: the name is NUL-terminated, and the value is an int, as
+    // documented by the sysctl
+    let result = unsafe {
+        libc::sysctlbyname(
+            c"kern.memorystatus_vm_pressure_level".as_ptr(),
+            &mut level as *mut libc::c_int as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error()).context("reading the memory pressure level failed");
+    }
+
+    let level = match level {
+        1 => "normal",
+        2 => "warning",
+        4 => "critical",
+        _ => "unknown",
+    };
+
+    Ok(MemoryPressure {
+        level: Some(level.to_string()),
+        some: None,
+        full: None,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_memory_pressure() -> Result<MemoryPressure> {
+    Err(crate::error::categorized(
+        crate::error::ErrorCode::UnsupportedPlatform,
+        "reading the memory pressure is not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty directory, unique to the calling test, to lay out
+    /// fixture files in.
+    #[cfg(target_os = "linux")]
+    fn fixture_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mymy-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn container_from_cgroup_tells_the_runtime() {
+        let docker = "12:pids:/docker/3f1c9a\n11:memory:/docker/3f1c9a\n0::/system.slice/containerd.service\n";
+        let kubernetes = "0::/kubepods/besteffort/pod1234/3f1c9a\n";
+        let podman = "0::/machine.slice/libpod-3f1c9a.scope\n";
+        let lxc = "0::/lxc.payload.web\n";
+
+        assert_eq!(container_from_cgroup(docker), Some("docker"));
+        assert_eq!(container_from_cgroup(kubernetes), Some("kubernetes"));
+        assert_eq!(container_from_cgroup(podman), Some("podman"));
+        assert_eq!(container_from_cgroup(lxc), Some("lxc"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn container_from_cgroup_ignores_hosts() {
+        assert_eq!(container_from_cgroup("0::/init.scope\n"), None);
+        assert_eq!(container_from_cgroup("12:pids:/user.slice/user-1000.slice\n"), None);
+        assert_eq!(container_from_cgroup(""), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn container_under_detects_dockerenv() {
+        let root = fixture_dir("dockerenv");
+        std::fs::write(root.join(".dockerenv"), "").unwrap();
+
+        assert_eq!(container_under(&root).as_deref(), Some("docker"));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn container_under_detects_the_init_environment_and_cgroup() {
+        let root = fixture_dir("container-proc");
+        std::fs::create_dir_all(root.join("proc/1")).unwrap();
+
+        std::fs::write(root.join("proc/1/cgroup"), "0::/kubepods/pod1234/3f1c9a\n").unwrap();
+        assert_eq!(container_under(&root).as_deref(), Some("kubernetes"));
+
+        std::fs::write(root.join("proc/1/environ"), b"HOME=/\0container=systemd-nspawn\0").unwrap();
+        assert_eq!(container_under(&root).as_deref(), Some("systemd-nspawn"));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn container_under_ignores_hosts() {
+        let root = fixture_dir("host");
+
+        assert_eq!(container_under(&root), None);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    fn parsed(distro: &str) -> (String, Option<String>) {
+        let os = Os::parse(distro);
+        assert_eq!(os.distro, distro);
+        (os.name, os.version)
+    }
+
+    #[test]
+    fn os_parse_splits_the_name_and_version() {
+        assert_eq!(parsed("Ubuntu 22.04.3 LTS"), ("Ubuntu".to_string(), Some("22.04.3".to_string())));
+        assert_eq!(parsed("Debian GNU/Linux 12 (bookworm)"), ("Debian GNU/Linux".to_string(), Some("12".to_string())));
+        assert_eq!(parsed("Alpine Linux v3.19"), ("Alpine Linux".to_string(), Some("3.19".to_string())));
+    }
+
+    #[test]
+    fn os_parse_keeps_the_whole_string_without_a_version() {
+        assert_eq!(parsed("Arch Linux"), ("Arch Linux".to_string(), None));
+        assert_eq!(parsed("10 Pro"), ("10 Pro".to_string(), None));
+        assert_eq!(parsed(""), ("".to_string(), None));
+    }
+
+    fn ram_trend_of(used: &[u64]) -> RamTrend {
+        RamTrend {
+            samples: used
+                .iter()
+                .enumerate()
+                .map(|(index, used)| RamSample {
+                    elapsed: Duration::from_secs(index as u64),
+                    used: Bytes(*used),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn ram_trend_summarizes_the_samples() {
+        let trend = ram_trend_of(&[3000, 1000, 4000, 2000]);
+
+        let serialized = serde_json::to_value(&trend).unwrap();
+        assert_eq!(serialized["first_used_ram_bytes"], 3000);
+        assert_eq!(serialized["last_used_ram_bytes"], 2000);
+        assert_eq!(serialized["min_used_ram_bytes"], 1000);
+        assert_eq!(serialized["max_used_ram_bytes"], 4000);
+        assert_eq!(serialized["delta_used_ram_bytes"], -1000);
+        assert_eq!(serialized["duration_secs"], 3.0);
+        assert_eq!(serialized["samples"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn ram_trend_of_no_samples_is_empty() {
+        let trend = ram_trend_of(&[]);
+
+        assert_eq!((trend.first(), trend.last(), trend.min(), trend.max(), trend.delta()), (0, 0, 0, 0, 0));
+        assert_eq!(trend.duration(), Duration::ZERO);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_memory_pressure_reads_both_stalls() {
+        let psi = "\
+some avg10=1.25 avg60=0.50 avg300=0.10 total=123456
+full avg10=0.30 avg60=0.05 avg300=0.00 total=7890
+";
+
+        let pressure = parse_memory_pressure(psi).unwrap();
+        let some = pressure.some.unwrap();
+        let full = pressure.full.unwrap();
+
+        assert_eq!((some.avg10, some.avg60, some.avg300, some.total_us), (1.25, 0.50, 0.10, 123456));
+        assert_eq!((full.avg10, full.avg60, full.avg300, full.total_us), (0.30, 0.05, 0.00, 7890));
+        assert!(pressure.level.is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_memory_pressure_rejects_malformed_values() {
+        let err = parse_memory_pressure("some avg10=high avg60=0.00 avg300=0.00 total=0\n").err().unwrap();
+        assert_eq!(err.to_string(), "invalid avg10 value \"high\"");
+
+        assert!(parse_memory_pressure("some avg10\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn uptime_boot_time_and_uptime_add_up_to_now() {
+        let uptime = uptime().await.unwrap();
+
+        let booted_for = Local::now() - uptime.boot_time;
+        let drift = (booted_for.num_seconds() - uptime.uptime.as_secs() as i64).abs();
+        assert!(drift <= 5, "boot time and uptime are {}s apart", drift);
+    }
+
+    #[tokio::test]
+    async fn ram_trend_stops_sampling_once_cancelled() {
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let started_at = Instant::now();
+        let trend = ram_trend(10, Duration::from_secs(30), &token).await.unwrap();
+
+        assert_eq!(trend.samples.len(), 1);
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+    }
+
+    fn ram_of(total: u64, used: u64, available: u64) -> Ram {
+        Ram {
+            total: Bytes(total),
+            used: Bytes(used),
+            free: Bytes(total - used),
+            available: Bytes(available),
+            cached: None,
+            buffers: None,
+        }
+    }
+
+    #[test]
+    fn ram_percentages_are_shares_of_the_total() {
+        let ram = ram_of(3000, 1000, 1500);
+        assert!((ram.used_percent() - 33.333).abs() < 0.001);
+        assert_eq!(ram.available_percent(), 50.0);
+
+        let serialized = serde_json::to_value(&ram).unwrap();
+        assert_eq!(serialized["used_percent"], 33.3);
+        assert_eq!(serialized["available_percent"], 50.0);
+
+        assert_eq!(percent_of(10, 0), 0.0);
+    }
+
+    #[test]
+    fn ram_warns_when_little_is_available() {
+        let low = ram_of(1000, 980, 20).to_string();
+        let healthy = ram_of(1000, 500, 400).to_string();
+
+        assert!(low.contains("warning: only 2.0% of the RAM is available"), "{}", low);
+        assert!(!healthy.contains("warning"), "{}", healthy);
+    }
+
+    #[tokio::test]
+    async fn cpus_list_every_logical_core() {
+        let cpu = cpus(false, false).await.unwrap().per_core(true);
+
+        assert_eq!(cpu.cores.len(), cpu.core_count);
+        assert_eq!(serde_json::to_value(&cpu).unwrap()["cores"].as_array().unwrap().len(), cpu.core_count);
+        assert_eq!(cpu.to_string().lines().filter(|line| line.starts_with("core ")).count(), cpu.core_count);
+    }
+
+    #[test]
+    fn cpu_serializes_both_core_counts() {
+        let cpu = Cpu {
+            brand: "Example CPU".to_string(),
+            core_count: 8,
+            physical_cores: Some(4),
+            frequency: 3200,
+            frequency_ghz: 3.2,
+            cores: Vec::new(),
+            caches: None,
+            features: None,
+            per_core: false,
+        };
+
+        let serialized = serde_json::to_value(&cpu).unwrap();
+        assert_eq!(serialized["core_count"], 8);
+        assert_eq!(serialized["physical_cores"], 4);
+        assert!(cpu.to_string().contains("4 physical / 8 logical cores"));
+
+        let unknown = Cpu { physical_cores: None, ..cpu };
+        assert_eq!(serde_json::to_value(&unknown).unwrap()["physical_cores"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use colored::Color;
+
+/// The color theme used to render the text output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Theme {
+    /// Bright colors, suited to terminals with a dark background.
+    Dark,
+
+    /// Darker colors, suited to terminals with a light background.
+    Light,
+
+    /// No colors at all.
+    None,
+}
+
+/// The colors used by the text output of the commands.
+pub struct Palette {
+    /// The color of the names of listed items, such as disks.
+    pub name: Color,
+
+    /// The color of values within healthy bounds.
+    pub good: Color,
+
+    /// The color of values approaching unhealthy bounds.
+    pub warning: Color,
+
+    /// The color of values beyond healthy bounds.
+    pub critical: Color,
+
+    /// The color of secondary information worth noticing, such as timezones.
+    pub accent: Color,
+
+    /// The color of measured values, such as clock offsets.
+    pub highlight: Color,
+
+    /// The color of complementary details, such as disk types.
+    pub muted: Color,
+}
+
+const DARK_PALETTE: Palette = Palette {
+    name: Color::Cyan,
+    good: Color::Green,
+    warning: Color::Yellow,
+    critical: Color::Red,
+    accent: Color::BrightCyan,
+    highlight: Color::BrightMagenta,
+    muted: Color::BrightWhite,
+};
+
+const LIGHT_PALETTE: Palette = Palette {
+    name: Color::Blue,
+    good: Color::Green,
+    warning: Color::Magenta,
+    critical: Color::Red,
+    accent: Color::Cyan,
+    highlight: Color::Blue,
+    muted: Color::Black,
+};
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Sets the theme used to render the text output.
+///
+/// The theme can only be set once, and should be set before any output is
+/// rendered. The `none` theme disables colors altogether.
+pub fn set(theme: Theme) {
+    if theme == Theme::None {
+        colored::control::set_override(false);
+    }
+
+    THEME.get_or_init(|| theme);
+}
+
+/// Returns the palette of the current theme, which defaults to the dark theme.
+pub fn palette() -> &'static Palette {
+    match THEME.get() {
+        Some(Theme::Light) => &LIGHT_PALETTE,
+        _ => &DARK_PALETTE,
+    }
+}